@@ -0,0 +1,26 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use pngme::chunk::Chunk;
+use pngme::png::Png;
+
+/// A serialized PNG with 256 IDAT chunks of 64 KiB each, 16 MiB in total.
+fn large_png_bytes() -> Vec<u8> {
+    let mut png = Png::from_chunks(vec![Chunk::try_new("IHDR", vec![0; 13]).unwrap()]);
+    for i in 0..256 {
+        png.append_chunk(Chunk::try_new("IDAT", vec![i as u8; 64 * 1024]).unwrap());
+    }
+    png.append_chunk(Chunk::try_new("IEND", Vec::new()).unwrap());
+    png.as_bytes()
+}
+
+fn parse(c: &mut Criterion) {
+    let bytes = large_png_bytes();
+
+    c.bench_function("parse_check_crc", |b| b.iter(|| Png::parser().parse(black_box(&bytes)).unwrap()));
+
+    c.bench_function("parse_skip_crc", |b| b.iter(|| {
+        Png::parser().check_crc(false).parse(black_box(&bytes)).unwrap()
+    }));
+}
+
+criterion_group!(benches, parse);
+criterion_main!(benches);
@@ -0,0 +1,38 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use pngme::chunk::Chunk;
+use pngme::png::Png;
+
+/// A PNG with 256 IDAT chunks of 64 KiB each, 16 MiB in total.
+fn large_png() -> Png {
+    let mut png = Png::from_chunks(vec![Chunk::try_new("IHDR", vec![0; 13]).unwrap()]);
+    for i in 0..256 {
+        png.append_chunk(Chunk::try_new("IDAT", vec![i as u8; 64 * 1024]).unwrap());
+    }
+    png.append_chunk(Chunk::try_new("IEND", Vec::new()).unwrap());
+    png
+}
+
+fn serialize(c: &mut Criterion) {
+    let png = large_png();
+
+    c.bench_function("as_bytes", |b| b.iter(|| black_box(&png).as_bytes()));
+
+    // The previous implementation, which built a buffer per chunk and
+    // grew the result as it went
+    c.bench_function("as_bytes_per_chunk", |b| b.iter(|| {
+        let mut res = Png::STANDARD_HEADER.to_vec();
+        for chunk in black_box(&png).chunks() {
+            res.append(&mut chunk.as_bytes());
+        }
+        res
+    }));
+
+    c.bench_function("write_to_writer", |b| b.iter(|| {
+        let mut res = Vec::new();
+        black_box(&png).write_to_writer(&mut res).unwrap();
+        res
+    }));
+}
+
+criterion_group!(benches, serialize);
+criterion_main!(benches);
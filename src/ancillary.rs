@@ -0,0 +1,82 @@
+use crate::chunk::Chunk;
+
+/// Renders the data of a known ancillary chunk in a human readable form.
+/// Returns `None` for unknown chunk types or data of the wrong size, in
+/// which case callers should fall back to the raw preview.
+pub fn describe(chunk: &Chunk) -> Option<String> {
+    match chunk.chunk_type().to_string().as_str() {
+        "tIME" => describe_time(chunk.data()),
+        "gAMA" => describe_gama(chunk.data()),
+        "bKGD" => describe_bkgd(chunk.data()),
+        _ => None,
+    }
+}
+
+fn describe_time(v: &[u8]) -> Option<String> {
+    if v.len() != 7 {
+        return None;
+    }
+    let year = u16::from_be_bytes([v[0], v[1]]);
+    Some(format!("{:04}-{:02}-{:02} {:02}:{:02}:{:02}", year, v[2], v[3], v[4], v[5], v[6]))
+}
+
+fn describe_gama(v: &[u8]) -> Option<String> {
+    let gamma = u32::from_be_bytes(v.try_into().ok()?);
+    Some(format!("{:.5}", gamma as f64 / 100000.0))
+}
+
+/// The layout of bKGD depends on the color type, which shows in the
+/// data length: a palette index, a gray level, or an RGB triple.
+fn describe_bkgd(v: &[u8]) -> Option<String> {
+    match v.len() {
+        1 => Some(format!("palette index {}", v[0])),
+        2 => Some(format!("gray {}", u16::from_be_bytes([v[0], v[1]]))),
+        6 => Some(format!("rgb({}, {}, {})",
+            u16::from_be_bytes([v[0], v[1]]),
+            u16::from_be_bytes([v[2], v[3]]),
+            u16::from_be_bytes([v[4], v[5]]),
+        )),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk_type::ChunkType;
+    use std::str::FromStr;
+
+    fn chunk(chunk_type: &str, data: Vec<u8>) -> Chunk {
+        Chunk::new(ChunkType::from_str(chunk_type).unwrap(), data)
+    }
+
+    #[test]
+    fn test_describe_time() {
+        let time = chunk("tIME", vec![0x07, 0xe8, 1, 15, 10, 30, 0]);
+        assert_eq!(describe(&time).as_deref(), Some("2024-01-15 10:30:00"));
+    }
+
+    #[test]
+    fn test_describe_gama() {
+        let gama = chunk("gAMA", 45455u32.to_be_bytes().to_vec());
+        assert_eq!(describe(&gama).as_deref(), Some("0.45455"));
+    }
+
+    #[test]
+    fn test_describe_bkgd() {
+        assert_eq!(describe(&chunk("bKGD", vec![3])).as_deref(), Some("palette index 3"));
+        assert_eq!(describe(&chunk("bKGD", vec![0, 255])).as_deref(), Some("gray 255"));
+        assert_eq!(
+            describe(&chunk("bKGD", vec![0, 255, 0, 128, 0, 0])).as_deref(),
+            Some("rgb(255, 128, 0)")
+        );
+    }
+
+    #[test]
+    fn test_describe_falls_back() {
+        assert_eq!(describe(&chunk("RuSt", vec![1, 2, 3])), None);
+        assert_eq!(describe(&chunk("tIME", vec![0; 6])), None);
+        assert_eq!(describe(&chunk("gAMA", vec![0; 3])), None);
+        assert_eq!(describe(&chunk("bKGD", vec![0; 4])), None);
+    }
+}
@@ -1,4 +1,7 @@
-use clap::{Args, Parser, Subcommand};
+use clap::{ArgEnum, Args, Parser, Subcommand};
+use clap_complete::Shell;
+
+use crate::examples;
 
 #[derive(Parser)]
 #[clap(author, version, about)]
@@ -6,6 +9,10 @@ use clap::{Args, Parser, Subcommand};
 pub struct Cli {
     #[clap(subcommand)]
     pub command: Commands,
+    /// Describe each step on stderr, e.g. the bytes read and written and
+    /// the chunks added or removed
+    #[clap(short, long, global = true)]
+    pub verbose: bool,
 }
 
 #[derive(Subcommand)]
@@ -16,11 +23,13 @@ pub enum Commands {
     /// inside the PNG file. A single invocation can add a single
     /// secret-message containing chunks. Multiple invocations
     /// can be used to add multiple chunks.
+    #[clap(after_help = examples::ENCODE)]
     Encode(EncodeArgs),
     /// Decode the secret message from a PNG file.
     /// 
     /// The message is only extracted and displayed. The input file
     /// is not modified.
+    #[clap(after_help = examples::DECODE)]
     Decode(DecodeArgs),
     /// Remove the embedded secret message(s) from a PNG file
     /// 
@@ -28,24 +37,163 @@ pub enum Commands {
     /// If there are multiple embedded messages, multiple
     /// invocations need to be used even if they have the same
     /// chunk-type.
+    #[clap(after_help = examples::REMOVE)]
     Remove(RemoveArgs),
     /// Dump all chunks inside the PNG file
     /// 
     /// This is useful for debugging. Currently, data is also
     /// dumped as HEX array. The output is NOT easily parseable
     /// programmatically. This might be changed in future!
+    #[clap(after_help = examples::PRINT)]
     Print(PrintArgs),
+    /// Export a single chunk to a file
+    /// 
+    /// The chunk is written in its complete serialized form (length,
+    /// chunk-type, data and CRC) without the PNG signature, so that
+    /// it can be imported into another PNG file later.
+    #[clap(after_help = examples::EXPORT)]
+    Export(ExportArgs),
+    /// Import a chunk previously written by export into a PNG file
+    /// 
+    /// The chunk is checked for a valid chunk-type and CRC and is
+    /// inserted just before the IEND chunk.
+    #[clap(after_help = examples::IMPORT)]
+    Import(ImportArgs),
+    /// Print a checksum of the whole PNG file
+    /// 
+    /// The checksum is computed over the file as pngme would write
+    /// it, so it can be used to verify round-trips and to detect
+    /// tampering. sha256 is only available when built with the
+    /// `sha256` feature.
+    #[clap(after_help = examples::CHECKSUM)]
+    Checksum(ChecksumArgs),
+    /// Strip every ancillary chunk from a PNG file
+    /// 
+    /// Only the critical chunks (IHDR, PLTE, IDAT and IEND) and the
+    /// types listed with `--keep` are kept, in their original order.
+    /// Data appended after IEND is dropped too. This removes text,
+    /// color profiles and any hidden messages before an image is
+    /// published.
+    #[clap(after_help = examples::SANITIZE)]
+    Sanitize(SanitizeArgs),
+    /// Change the type of an ancillary chunk
+    /// 
+    /// The first chunk of the old type gets the new type and its CRC
+    /// is recomputed, the data is left as is. Critical chunks cannot
+    /// be renamed.
+    #[clap(after_help = examples::RENAME_TYPE)]
+    RenameType(RenameArgs),
+    /// Add a standard tEXt metadata chunk to a PNG file
+    /// 
+    /// The chunk holds a keyword and a text, both Latin-1, and is
+    /// inserted just before the IEND chunk.
+    #[clap(after_help = examples::SET_TEXT)]
+    SetText(SetTextArgs),
+    /// Rewrite a PNG file in a canonical form
+    /// 
+    /// Every chunk is written back with a CRC recomputed from its type
+    /// and data, whatever was stored in the input. Files holding the
+    /// same chunks therefore serialize to identical bytes, which makes
+    /// the output suitable for diffing and integrity baselines.
+    #[clap(after_help = examples::CANONICALIZE)]
+    Canonicalize(CanonicalizeArgs),
+    /// Remove repeated ancillary chunks from a PNG file
+    /// 
+    /// Ancillary chunks with the same type and data as another chunk
+    /// are removed, keeping only one chunk of each group. Critical
+    /// chunks are left alone.
+    #[clap(after_help = examples::DEDUP)]
+    Dedup(DedupArgs),
+    /// Remove any data appended after the IEND chunk
+    /// 
+    /// The chunks are written back unchanged, only the bytes following
+    /// IEND are dropped. Such bytes are often junk added by other tools,
+    /// or a payload hidden where image viewers do not look.
+    #[clap(after_help = examples::STRIP_TRAILING)]
+    StripTrailing(StripTrailingArgs),
+    /// Print the chunks of a PNG file again whenever it changes
+    /// 
+    /// The file's modification time is polled, and on every change the
+    /// screen is cleared and the chunks are printed as by `print
+    /// --brief`. Errors, e.g. while another tool is still writing the
+    /// file, are shown without stopping. Press Ctrl-C to exit.
+    #[clap(after_help = examples::WATCH)]
+    Watch(WatchArgs),
+    /// Encode a different message into each of many PNG files
+    /// 
+    /// The manifest has one `path<TAB>message` line per file; empty
+    /// lines are skipped. Each message is inserted before the IEND
+    /// chunk of its file, which is modified in place. The whole
+    /// manifest is checked before any file is touched, and every file
+    /// is reported as done or failed.
+    #[clap(after_help = examples::BATCH_ENCODE)]
+    BatchEncode(BatchEncodeArgs),
+    /// Generate a shell completion script
+    /// 
+    /// The script is written to stdout, e.g.
+    /// `pngme completions zsh > _pngme`.
+    #[clap(hide = true, after_help = examples::COMPLETIONS)]
+    Completions(CompletionsArgs),
 }
 #[derive(Args, Debug)]
 pub struct EncodeArgs {
-    /// Path to the input PNG file
-    pub input_file_path: String,
-    /// Four byte valid ASCII string for chunk type
-    pub chunk_type_str: String,
+    /// Path to the input PNG file. Left out when `--pipe` is used
+    pub input_file_path: Option<String>,
+    /// Four byte valid ASCII string for chunk type. Left out when
+    /// `--random-type` is used
+    pub chunk_type_str: Option<String>,
     /// A UTF-8 message string
-    pub message: String,
+    pub message: Option<String>,
     /// Path to the output PNG file. If not specified, input file is used
     pub output_file_path: Option<String>,
+    /// Pick a random ancillary, private, safe-to-copy chunk type and
+    /// print it. The chunk type argument must then be left out
+    #[clap(long)]
+    pub random_type: bool,
+    /// Seed for `--random-type`, to generate the same chunk type again
+    #[clap(long, requires = "random-type")]
+    pub seed: Option<u64>,
+    /// Refuse chunk types that do not conform to the PNG spec instead of
+    /// only warning about them
+    #[clap(long)]
+    pub strict: bool,
+    /// Read the PNG from stdin and write the result to stdout. The input
+    /// and output file paths must then be left out
+    #[clap(long)]
+    pub pipe: bool,
+    /// Append the chunk at the very end of the file instead of right
+    /// before the IEND chunk
+    #[clap(long)]
+    pub no_iend_move: bool,
+    /// Insert the chunk right before the first IDAT chunk, for chunk types
+    /// that must come before the image data. Fails if there is no IDAT
+    #[clap(long, conflicts_with = "no-iend-move")]
+    pub before_idat: bool,
+    /// Encrypt the message with a key derived from `--passphrase`. Only
+    /// available when built with the `encrypt` feature
+    #[clap(long, requires = "passphrase")]
+    pub encrypt: bool,
+    /// Passphrase for `--encrypt`
+    #[clap(long)]
+    pub passphrase: Option<String>,
+    /// Never modify the input file. Unless an output file path is given,
+    /// which takes precedence, the result is written next to the input
+    /// as `<name>.embedded.png`
+    #[clap(long, conflicts_with = "pipe")]
+    pub copy: bool,
+    /// Replace the data of the first chunk of the same type if there is
+    /// one, instead of adding another chunk. Running encode again then
+    /// leaves a single chunk of that type
+    #[clap(long, alias = "replace-if-exists")]
+    pub upsert: bool,
+    /// Leave the input file untouched, modification time included, when
+    /// the result is identical to it, e.g. after an `--upsert` with the
+    /// same message. By default it is always written back
+    #[clap(long)]
+    pub write_if_changed: bool,
+    /// Write the file back even when nothing changed. This is the default
+    #[clap(long, conflicts_with = "write-if-changed")]
+    pub always_write: bool,
 }
 #[derive(Args, Debug)]
 
@@ -53,19 +201,344 @@ pub struct DecodeArgs {
     /// Path to the input PNG file
     pub input_file_path: String,
     /// Four byte valid ASCII string for chunk type
-    pub chunk_type_str: String,
+    #[clap(required_unless_present_any = &["glob", "trailing"])]
+    pub chunk_type_str: Option<String>,
+    /// Select chunks by a pattern instead of an exact chunk type. `?`
+    /// matches any single letter and `*` any run of letters; the pattern
+    /// is matched against the literal ASCII bytes, so case matters
+    #[clap(long, conflicts_with = "chunk-type-str")]
+    pub glob: Option<String>,
+    /// Decode every chunk of the given type instead of only the first one
+    #[clap(long)]
+    pub all: bool,
+    /// Decode the last chunk of the given type instead of the first one,
+    /// i.e. the message added by the latest encode
+    #[clap(long, conflicts_with = "all")]
+    pub last: bool,
+    /// With `--all`, fail before printing anything unless exactly this
+    /// many chunks match
+    #[clap(long, requires = "all")]
+    pub expect_count: Option<usize>,
+    /// Separator written after each decoded message. The escapes `\n`,
+    /// `\t` and `\0` are recognised
+    #[clap(long, default_value = "\\n")]
+    pub delimiter: String,
+    /// Replace invalid UTF-8 in the message instead of failing
+    #[clap(long)]
+    pub lossy: bool,
+    /// Print at most this many characters of each message, followed by
+    /// `…` when it is longer
+    #[clap(long)]
+    pub max_chars: Option<usize>,
+    /// Print nothing, only exit with status 0 if a chunk of the given
+    /// type exists and 1 otherwise
+    #[clap(long)]
+    pub exists: bool,
+    /// Read the message even if the file contains chunks with a bad CRC.
+    /// A warning is printed for every decoded chunk whose CRC is wrong
+    #[clap(long)]
+    pub ignore_crc: bool,
+    /// Decrypt a message written with `encode --encrypt`. Only available
+    /// when built with the `encrypt` feature
+    #[clap(long, requires = "passphrase")]
+    pub decrypt: bool,
+    /// Passphrase for `--decrypt`
+    #[clap(long)]
+    pub passphrase: Option<String>,
+    /// Give up on files with more than this many chunks. Defaults to one
+    /// million
+    #[clap(long)]
+    pub max_chunks: Option<usize>,
+    /// Container format of the input. Only `png` is implemented so far,
+    /// `auto` picks it for every file
+    #[clap(long, arg_enum, default_value = "auto")]
+    pub input_format: InputFormat,
+    /// Extract the bytes hidden after the IEND chunk instead of a chunk
+    #[clap(long, conflicts_with_all = &["chunk-type-str", "glob"])]
+    pub trailing: bool,
+    /// Write the bytes extracted with `--trailing` to this file instead of
+    /// stdout
+    #[clap(long, requires = "trailing")]
+    pub output: Option<String>,
+    /// Show on stderr how much of the file has been read, for very large
+    /// files. Ignored when stderr is not a terminal
+    #[clap(long)]
+    pub progress: bool,
 }
 #[derive(Args, Debug)]
 
 pub struct RemoveArgs {
+    /// Path to the input PNG file. Left out when `--pipe` is used
+    pub input_file_path: Option<String>,
+    /// Four byte valid ASCII string for chunk type. Left out when
+    /// `--glob` is used
+    pub chunk_type_str: Option<String>,
+    /// Remove every chunk whose type matches the pattern. `?` matches
+    /// any single letter and `*` any run of letters; the pattern is
+    /// matched against the literal ASCII bytes, so case matters
+    #[clap(long)]
+    pub glob: Option<String>,
+    /// Read the PNG from stdin and write the result to stdout. The input
+    /// file path must then be left out
+    #[clap(long)]
+    pub pipe: bool,
+    /// Succeed and write the file unchanged when no chunk matches,
+    /// instead of failing
+    #[clap(long)]
+    pub if_present: bool,
+    /// Remove every chunk whose type has the same letters as the given
+    /// one in any case, e.g. `RuSt` and `rust` for `ruSt`
+    #[clap(long, conflicts_with = "glob")]
+    pub normalize_case: bool,
+    /// Leave the input file untouched, modification time included, when
+    /// the result is identical to it, e.g. when `--if-present` finds
+    /// nothing to remove. By default it is always written back
+    #[clap(long)]
+    pub write_if_changed: bool,
+    /// Write the file back even when nothing changed. This is the default
+    #[clap(long, conflicts_with = "write-if-changed")]
+    pub always_write: bool,
+}
+#[derive(Args, Debug)]
+
+pub struct PrintArgs {
+    /// Path to the input PNG file
+    pub input_file_path: String,
+    /// Print the chunks that could be read from a damaged or truncated
+    /// file instead of failing
+    #[clap(long)]
+    pub best_effort: bool,
+    /// Show on stderr how much of the file has been read, for very large
+    /// files. Ignored when stderr is not a terminal
+    #[clap(long)]
+    pub progress: bool,
+    /// Mark each chunk's CRC as OK or BAD. Chunks with a bad CRC are
+    /// read instead of failing, so that damaged chunks can be found
+    #[clap(long)]
+    pub check_crc: bool,
+    /// Do not compute any CRC while reading, which is faster for large
+    /// files that are trusted. Stored CRCs are printed as they are
+    #[clap(long, conflicts_with = "check-crc")]
+    pub no_crc_check: bool,
+    /// Read chunk types made of any four bytes instead of failing, for
+    /// files that do not conform to the spec. Bytes that are not letters
+    /// are printed escaped, e.g. `\x01`
+    #[clap(long)]
+    pub allow_non_ascii_type: bool,
+    /// Finish with a line giving the number of chunks and their total size
+    #[clap(long)]
+    pub count: bool,
+    /// Show sizes in binary units (KiB, MiB, ...) instead of bytes
+    #[clap(short = 'H', long)]
+    pub human: bool,
+    /// Start with a summary of the image, e.g. its size and color type,
+    /// followed by known ancillary chunks such as tIME, gAMA and bKGD
+    #[clap(long)]
+    pub info: bool,
+    /// Leave the chunk data out of the dump
+    #[clap(long)]
+    pub brief: bool,
+    /// Dump at most this many bytes of data per chunk
+    #[clap(long)]
+    pub limit: Option<usize>,
+    /// Highlight critical and ancillary chunk types with colors
+    #[clap(long)]
+    pub color: bool,
+    /// Only dump the chunks whose type matches the pattern. `?` matches
+    /// any single letter and `*` any run of letters; the pattern is
+    /// matched against the literal ASCII bytes, so case matters
+    #[clap(long)]
+    pub glob: Option<String>,
+    /// Dump the chunks sorted by chunk type instead of in file order.
+    /// Chunks of the same type keep their relative order
+    #[clap(long)]
+    pub sort: bool,
+    /// Only list the chunks whose type breaks the spec, i.e. has the
+    /// reserved bit set or is an unknown critical type, with the reason.
+    /// Nothing is printed for a clean file
+    #[clap(long)]
+    pub show_invalid: bool,
+    /// Output format. `raw` writes a binary record per chunk to stdout:
+    /// the 4-byte chunk type, the data length as a 4-byte big-endian
+    /// integer, then the data. There is no signature and no CRC. `json`
+    /// writes an object per chunk with its type, length, CRC and hex
+    /// data, plus `crc_valid` with `--check-crc`. In both, `--info` and
+    /// `--count` are ignored
+    #[clap(long, arg_enum, default_value = "text")]
+    pub format: PrintFormat,
+    /// Dump the whole file as hex instead, labelling the signature and
+    /// the length, type, data and CRC fields of every chunk with their
+    /// offsets
+    #[clap(long)]
+    pub pretty_hex: bool,
+    /// Follow each chunk type with the properties encoded in the case of
+    /// its letters, e.g. whether it is critical or safe to copy
+    #[clap(long)]
+    pub explain: bool,
+    /// Start each chunk line with the position of the chunk in the file,
+    /// counting from 0. Cannot be combined with `--glob` or `--sort`,
+    /// which would change the positions
+    #[clap(short = 'i', long, conflicts_with_all = &["glob", "sort"])]
+    pub index: bool,
+    /// Base in which CRCs are printed
+    #[clap(long, arg_enum, default_value = "hex")]
+    pub base: Base,
+    /// Give up on files with more than this many chunks. Defaults to one
+    /// million
+    #[clap(long)]
+    pub max_chunks: Option<usize>,
+    /// Container format of the input. Only `png` is implemented so far,
+    /// `auto` picks it for every file
+    #[clap(long, arg_enum, default_value = "auto")]
+    pub input_format: InputFormat,
+}
+#[derive(Args, Debug)]
+
+pub struct ExportArgs {
     /// Path to the input PNG file
     pub input_file_path: String,
     /// Four byte valid ASCII string for chunk type
     pub chunk_type_str: String,
+    /// Path to the file the chunk is written to
+    pub output_file_path: String,
 }
 #[derive(Args, Debug)]
 
-pub struct PrintArgs {
+pub struct ImportArgs {
+    /// Path to the input PNG file
+    pub input_file_path: String,
+    /// Path to a file containing a single serialized chunk
+    pub chunk_file_path: String,
+    /// Path to the output PNG file. If not specified, input file is used
+    pub output_file_path: Option<String>,
+}
+#[derive(Args, Debug)]
+
+pub struct CompletionsArgs {
+    /// Shell to generate the completion script for
+    #[clap(arg_enum)]
+    pub shell: Shell,
+}
+#[derive(Args, Debug)]
+
+pub struct ChecksumArgs {
+    /// Path to the input PNG file
+    pub input_file_path: String,
+    /// Checksum algorithm to use
+    #[clap(long, arg_enum, default_value = "crc32")]
+    pub algorithm: ChecksumAlgorithm,
+}
+#[derive(Args, Debug)]
+
+pub struct SanitizeArgs {
+    /// Path to the input PNG file
+    pub input_file_path: String,
+    /// Path to the output PNG file. If not specified, input file is used
+    pub output_file_path: Option<String>,
+    /// Keep ancillary chunks of this type as well. Can be given several
+    /// times
+    #[clap(long, value_name = "TYPE")]
+    pub keep: Vec<String>,
+    /// Leave the input file untouched, modification time included, when
+    /// there is nothing to strip. By default it is always written back
+    #[clap(long)]
+    pub write_if_changed: bool,
+    /// Write the file back even when nothing changed. This is the default
+    #[clap(long, conflicts_with = "write-if-changed")]
+    pub always_write: bool,
+}
+#[derive(Args, Debug)]
+
+pub struct RenameArgs {
+    /// Path to the input PNG file
+    pub input_file_path: String,
+    /// Four byte valid ASCII string for the current chunk type
+    pub old_type: String,
+    /// Four byte valid ASCII string for the new chunk type
+    pub new_type: String,
+    /// Path to the output PNG file. If not specified, input file is used
+    pub output_file_path: Option<String>,
+}
+#[derive(Args, Debug)]
+
+pub struct SetTextArgs {
+    /// Path to the input PNG file
+    pub input_file_path: String,
+    /// Keyword of 1 to 79 Latin-1 characters, e.g. `Comment` or `Author`
+    pub keyword: String,
+    /// Latin-1 text stored under the keyword
+    pub text: String,
+    /// Path to the output PNG file. If not specified, input file is used
+    pub output_file_path: Option<String>,
+}
+#[derive(Args, Debug)]
+
+pub struct CanonicalizeArgs {
+    /// Path to the input PNG file
+    pub input_file_path: String,
+    /// Path to the output PNG file. If not specified, input file is used
+    pub output_file_path: Option<String>,
+}
+#[derive(Args, Debug)]
+
+pub struct DedupArgs {
     /// Path to the input PNG file
     pub input_file_path: String,
-}
\ No newline at end of file
+    /// Path to the output PNG file. If not specified, input file is used
+    pub output_file_path: Option<String>,
+    /// Which chunk of each group of duplicates to keep, which matters
+    /// when the position of a chunk is significant
+    #[clap(long, arg_enum, default_value = "first")]
+    pub dedupe_keep: DedupeKeep,
+}
+#[derive(Args, Debug)]
+
+pub struct StripTrailingArgs {
+    /// Path to the input PNG file
+    pub input_file_path: String,
+    /// Path to the output PNG file. If not specified, input file is used
+    pub output_file_path: Option<String>,
+}
+#[derive(Args, Debug)]
+
+pub struct WatchArgs {
+    /// Path to the PNG file to watch
+    pub input_file_path: String,
+    /// How often the file is checked for changes, in milliseconds
+    #[clap(long, default_value = "500")]
+    pub interval: u64,
+}
+#[derive(Args, Debug)]
+
+pub struct BatchEncodeArgs {
+    /// Path to the manifest of tab-separated paths and messages
+    pub manifest_path: String,
+    /// Four byte valid ASCII string for chunk type, used for every file
+    pub chunk_type_str: String,
+}
+#[derive(ArgEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChecksumAlgorithm {
+    Crc32,
+    Sha256,
+}
+#[derive(ArgEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DedupeKeep {
+    First,
+    Last,
+}
+#[derive(ArgEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PrintFormat {
+    Text,
+    Raw,
+    Json,
+}
+#[derive(ArgEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Base {
+    Dec,
+    Hex,
+}
+#[derive(ArgEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InputFormat {
+    Auto,
+    Png,
+}
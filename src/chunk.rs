@@ -1,11 +1,11 @@
 
-use std::{fmt::{Formatter, Display}, string::FromUtf8Error, error::Error};
+use std::{fmt::{Formatter, Display}, string::FromUtf8Error, error::Error, io::{self, Write}, str::FromStr};
 use crc::{Crc, CRC_32_ISO_HDLC};
 
 use crate::chunk_type::{ChunkType, ChunkTypeError};
 const CRC: Crc<u32> = Crc::<u32>::new(&CRC_32_ISO_HDLC);
 
-#[derive(Debug, PartialEq, Eq, Clone)]
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
 pub struct Chunk {
     length: u32,
     chunk_type: ChunkType,
@@ -17,9 +17,11 @@ pub struct Chunk {
 pub enum ChunkError {
     BadLen,
     BadDataLen,
+    LengthTooLarge,
     ChunkType(ChunkTypeError),
     BadCrc,
     Utf8(FromUtf8Error),
+    Malformed,
 }
 
 impl Display for ChunkError {
@@ -27,6 +29,7 @@ impl Display for ChunkError {
         match self {
             ChunkError::BadLen => write!(f, "Too few bytes to parse as a chunk"),
             ChunkError::BadDataLen => write!(f, "Data length does not match header"),
+            ChunkError::LengthTooLarge => write!(f, "Length field exceeds the maximum of {} bytes", Chunk::MAX_LENGTH),
             ChunkError::BadCrc => write!(f, "CRC mismatch"),
             ChunkError::ChunkType(c) => {
                 write!(f, "ChunkTypeError: ")?;
@@ -36,6 +39,7 @@ impl Display for ChunkError {
                 write!(f, "Error parsing data as utf-8: ")?;
                 e.fmt(f)
             }
+            ChunkError::Malformed => write!(f, "Expected a chunk in the form TYPE:message"),
         }
     }
 }
@@ -49,6 +53,8 @@ impl Chunk {
     pub const NON_DATA_FIELDS_COMBINED_BYTES: usize = Self::LENGTH_FIELD_BYTES
         + Self::CHUNK_TYPE_FIELD_BYTES
         + Self::CRC_FIELD_BYTES;
+    /// The PNG spec limits the length field to 2^31 - 1.
+    pub const MAX_LENGTH: u32 = (1 << 31) - 1;
 
     fn crc_digest(chunk_type_slice: &[u8], data_slice: &[u8]) -> u32 {
         let mut d = CRC.digest();
@@ -57,6 +63,12 @@ impl Chunk {
         d.finalize()
     }
 
+    /// Computes the CRC a chunk with this type and data would have,
+    /// without building the chunk.
+    pub fn compute_crc(chunk_type: &ChunkType, data: &[u8]) -> u32 {
+        Self::crc_digest(&chunk_type.bytes(), data)
+    }
+
     pub fn new(chunk_type: ChunkType, data: Vec<u8>) -> Chunk {
         let crc = Self::crc_digest(&chunk_type.bytes(), data.as_ref());
         Self {
@@ -67,6 +79,24 @@ impl Chunk {
         }
     }
 
+    /// Builds a chunk from a chunk type string, failing if the string is
+    /// not a valid chunk type.
+    pub fn try_new(chunk_type_str: &str, data: Vec<u8>) -> Result<Chunk, ChunkError> {
+        let chunk_type = ChunkType::from_str(chunk_type_str).map_err(ChunkError::ChunkType)?;
+        Ok(Self::new(chunk_type, data))
+    }
+
+    /// Builds a chunk with the given CRC instead of computing it, e.g. to
+    /// reproduce a damaged chunk. Use `is_crc_valid` to check it.
+    pub fn from_parts(chunk_type: ChunkType, data: Vec<u8>, crc: u32) -> Chunk {
+        Self {
+            length: data.len() as u32,
+            chunk_type,
+            data,
+            crc,
+        }
+    }
+
     pub fn length(&self) -> u32 {
         self.length
     }
@@ -83,37 +113,125 @@ impl Chunk {
         self.crc
     }
 
+    /// The length field as it is stored in the file, big-endian.
+    pub fn length_bytes(&self) -> [u8; 4] {
+        self.length.to_be_bytes()
+    }
+
+    /// The CRC field as it is stored in the file, big-endian.
+    pub fn crc_bytes(&self) -> [u8; 4] {
+        self.crc.to_be_bytes()
+    }
+
+    /// Size of the chunk once serialized, including the length, type and
+    /// CRC fields.
+    pub fn total_size(&self) -> usize {
+        Self::NON_DATA_FIELDS_COMBINED_BYTES + self.data.len()
+    }
+
+    /// Replaces the chunk data, updating the length and CRC to match.
+    pub fn set_data(&mut self, data: Vec<u8>) {
+        self.crc = Self::crc_digest(&self.chunk_type.bytes(), data.as_ref());
+        self.length = data.len() as u32;
+        self.data = data;
+    }
+
+    /// Changes the chunk type, updating the CRC which covers it.
+    pub fn set_type(&mut self, chunk_type: ChunkType) {
+        self.crc = Self::crc_digest(&chunk_type.bytes(), self.data.as_ref());
+        self.chunk_type = chunk_type;
+    }
+
+    /// Returns the chunk with its stored CRC replaced by the one computed
+    /// from its type and data, repairing a damaged CRC.
+    pub fn with_recomputed_crc(mut self) -> Chunk {
+        self.crc = Self::crc_digest(&self.chunk_type.bytes(), self.data.as_ref());
+        self
+    }
+
     pub fn data_as_string(&self) -> Result<String, ChunkError> {
         String::from_utf8(self.data().to_vec()).map_err(|e| ChunkError::Utf8(e))
     }
 
+    /// Like `data_as_string`, but replaces invalid UTF-8 sequences with
+    /// U+FFFD instead of failing.
+    pub fn data_as_string_lossy(&self) -> String {
+        String::from_utf8_lossy(self.data()).into_owned()
+    }
+
     pub fn as_bytes(&self) -> Vec<u8> {
-        self.length.to_be_bytes().iter()
-        .chain(self.chunk_type.bytes().iter())
-        .chain(self.data.iter())
-        .chain(self.crc.to_be_bytes().iter())
-        .copied()
-        .collect()
+        let mut bytes = Vec::with_capacity(self.total_size());
+        bytes.extend_from_slice(&self.length_bytes());
+        bytes.extend_from_slice(&self.chunk_type.bytes());
+        bytes.extend_from_slice(&self.data);
+        bytes.extend_from_slice(&self.crc_bytes());
+        bytes
     }
-}
 
-impl TryFrom<&[u8]> for Chunk {
-    type Error = ChunkError;
-    fn try_from(v: &[u8]) -> Result<Self, Self::Error> {
+    /// Serializes the chunk into a stack buffer, for small chunks such as
+    /// IEND whose size is known up front. Returns `None` unless
+    /// `total_size()` is exactly `N`.
+    pub fn as_array<const N: usize>(&self) -> Option<[u8; N]> {
+        if self.total_size() != N {
+            return None;
+        }
+        let mut bytes = [0; N];
+        let (length, rest) = bytes.split_at_mut(Self::LENGTH_FIELD_BYTES);
+        let (chunk_type, rest) = rest.split_at_mut(Self::CHUNK_TYPE_FIELD_BYTES);
+        let (data, crc) = rest.split_at_mut(self.data.len());
+        length.copy_from_slice(&self.length_bytes());
+        chunk_type.copy_from_slice(&self.chunk_type.bytes());
+        data.copy_from_slice(&self.data);
+        crc.copy_from_slice(&self.crc_bytes());
+        Some(bytes)
+    }
+
+    pub fn is_crc_valid(&self) -> bool {
+        self.crc == Self::crc_digest(&self.chunk_type.bytes(), self.data.as_ref())
+    }
+
+    /// Checks the chunk's integrity in one call, returning the first problem
+    /// found: a CRC that does not match, then a chunk type with the
+    /// reserved bit set.
+    pub fn validate(&self) -> Result<(), ChunkError> {
+        if !self.is_crc_valid() {
+            return Err(ChunkError::BadCrc);
+        }
+        if !self.chunk_type.is_valid() {
+            return Err(ChunkError::ChunkType(ChunkTypeError::ReservedBitInvalid));
+        }
+        Ok(())
+    }
+
+    /// Parses a chunk without verifying its CRC. The CRC read from `v` is
+    /// kept as is, so `is_crc_valid` tells whether the chunk is damaged.
+    pub fn try_from_lenient(v: &[u8]) -> Result<Chunk, ChunkError> {
+        Self::parse(v, false, false)
+    }
+
+    /// With `any_type`, the chunk type may be any four bytes, see
+    /// `ChunkType::from_bytes_unchecked`.
+    pub(crate) fn parse(v: &[u8], check_crc: bool, any_type: bool) -> Result<Chunk, ChunkError> {
         if v.len() < Self::NON_DATA_FIELDS_COMBINED_BYTES {
             return Err(ChunkError::BadLen);
         }
         let length = u32::from_be_bytes(v[..Self::LENGTH_FIELD_BYTES].try_into().unwrap());
+        if length > Self::MAX_LENGTH {
+            return Err(ChunkError::LengthTooLarge);
+        }
         if length as usize != v.len() - Self::NON_DATA_FIELDS_COMBINED_BYTES {
             return Err(ChunkError::BadDataLen);
         }
         let chunk_type_slice = &v[Self::LENGTH_FIELD_BYTES..Self::LENGTH_FIELD_BYTES + Self::CHUNK_TYPE_FIELD_BYTES];
         let data_slice = &v[Self::LENGTH_FIELD_BYTES + Self::CHUNK_TYPE_FIELD_BYTES .. v.len() - Self::CRC_FIELD_BYTES];
         let crc_slice = &v[v.len() - Self::CRC_FIELD_BYTES ..];
-        let chunk_type: ChunkType = chunk_type_slice.try_into().map_err(|e| ChunkError::ChunkType(e))?;
-        let crc_calculated = Self::crc_digest(chunk_type_slice, data_slice);
+        let chunk_type = if any_type {
+            ChunkType::from_bytes_unchecked(chunk_type_slice.try_into().unwrap())
+        } else {
+            chunk_type_slice.try_into().map_err(ChunkError::ChunkType)?
+        };
         let crc = u32::from_be_bytes(crc_slice.try_into().unwrap());
-        if crc != crc_calculated {
+        if check_crc && crc != Self::crc_digest(chunk_type_slice, data_slice) {
             return Err(ChunkError::BadCrc);
         }
         Ok(Self {
@@ -123,6 +241,32 @@ impl TryFrom<&[u8]> for Chunk {
             crc,
         })
     }
+
+    /// Writes the serialized chunk, producing the same bytes as `as_bytes`
+    /// without building them up in memory first.
+    pub fn write_to(&self, w: &mut impl Write) -> io::Result<()> {
+        w.write_all(&self.length_bytes())?;
+        w.write_all(&self.chunk_type.bytes())?;
+        w.write_all(&self.data)?;
+        w.write_all(&self.crc_bytes())
+    }
+}
+
+impl TryFrom<&[u8]> for Chunk {
+    type Error = ChunkError;
+    fn try_from(v: &[u8]) -> Result<Self, Self::Error> {
+        Self::parse(v, true, false)
+    }
+}
+
+/// Parses the `TYPE:message` shorthand, e.g. `ruSt:hello`. Everything
+/// after the first colon is the message.
+impl FromStr for Chunk {
+    type Err = ChunkError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (chunk_type_str, message) = s.split_once(':').ok_or(ChunkError::Malformed)?;
+        Self::try_new(chunk_type_str, message.as_bytes().to_vec())
+    }
 }
 
 impl Display for Chunk {
@@ -170,6 +314,84 @@ mod tests {
         assert_eq!(chunk.crc(), 2882656334);
     }
 
+    #[test]
+    fn test_compute_crc() {
+        let chunk_type = ChunkType::from_str("RuSt").unwrap();
+        let data = "This is where your secret message will be!".as_bytes();
+        assert_eq!(Chunk::compute_crc(&chunk_type, data), 2882656334);
+    }
+
+    #[test]
+    fn test_try_new_chunk() {
+        let data = "This is where your secret message will be!".as_bytes().to_vec();
+        let chunk = Chunk::try_new("RuSt", data).unwrap();
+        assert_eq!(chunk.chunk_type().to_string(), "RuSt");
+        assert_eq!(chunk.length(), 42);
+        assert_eq!(chunk.crc(), 2882656334);
+    }
+
+    #[test]
+    fn test_try_new_chunk_invalid_type() {
+        assert_eq!(
+            Chunk::try_new("Ru1t", vec![1, 2, 3]),
+            Err(ChunkError::ChunkType(ChunkTypeError::ByteOutOfRange))
+        );
+        assert_eq!(
+            Chunk::try_new("RuStRuSt", vec![1, 2, 3]),
+            Err(ChunkError::ChunkType(ChunkTypeError::BadLen))
+        );
+    }
+
+    #[test]
+    fn test_chunk_from_parts() {
+        let chunk_type = ChunkType::from_str("RuSt").unwrap();
+        let data = "This is where your secret message will be!".as_bytes().to_vec();
+        let chunk = Chunk::from_parts(chunk_type.clone(), data.clone(), 2882656334);
+        assert_eq!(chunk, Chunk::new(chunk_type.clone(), data.clone()));
+        assert!(chunk.is_crc_valid());
+
+        let chunk = Chunk::from_parts(chunk_type, data, 42);
+        assert_eq!(chunk.length(), 42);
+        assert_eq!(chunk.crc(), 42);
+        assert!(!chunk.is_crc_valid());
+    }
+
+    #[test]
+    fn test_chunk_from_str() {
+        let chunk: Chunk = "ruSt:hello: world".parse().unwrap();
+        assert_eq!(chunk.chunk_type().to_string(), "ruSt");
+        assert_eq!(chunk.data_as_string().unwrap(), "hello: world");
+        assert!(chunk.is_crc_valid());
+
+        let chunk: Chunk = "ruSt:".parse().unwrap();
+        assert_eq!(chunk.length(), 0);
+    }
+
+    #[test]
+    fn test_chunk_from_str_missing_colon() {
+        assert_eq!("ruSt hello".parse::<Chunk>(), Err(ChunkError::Malformed));
+    }
+
+    #[test]
+    fn test_chunk_from_str_bad_type() {
+        assert_eq!(
+            "ru1t:hello".parse::<Chunk>(),
+            Err(ChunkError::ChunkType(ChunkTypeError::ByteOutOfRange))
+        );
+    }
+
+    #[test]
+    fn test_chunk_set_type() {
+        let mut chunk = testing_chunk();
+        chunk.set_type(ChunkType::from_str("ruSt").unwrap());
+        assert_eq!(chunk.chunk_type().to_string(), "ruSt");
+        assert!(chunk.is_crc_valid());
+
+        let parsed = Chunk::try_from(chunk.as_bytes().as_ref()).unwrap();
+        assert_eq!(parsed, chunk);
+        assert_eq!(parsed.data(), testing_chunk().data());
+    }
+
     #[test]
     fn test_chunk_length() {
         let chunk = testing_chunk();
@@ -190,12 +412,84 @@ mod tests {
         assert_eq!(chunk_string, expected_chunk_string);
     }
 
+    #[test]
+    fn test_chunk_string_lossy() {
+        let chunk_type = ChunkType::from_str("RuSt").unwrap();
+        let chunk = Chunk::new(chunk_type, vec![72, 105, 0xff, 33]);
+        assert!(chunk.data_as_string().is_err());
+        assert_eq!(chunk.data_as_string_lossy(), "Hi\u{fffd}!");
+
+        let chunk = testing_chunk();
+        assert_eq!(chunk.data_as_string_lossy(), chunk.data_as_string().unwrap());
+    }
+
     #[test]
     fn test_chunk_crc() {
         let chunk = testing_chunk();
         assert_eq!(chunk.crc(), 2882656334);
     }
 
+    #[test]
+    fn test_chunk_total_size() {
+        let chunk = testing_chunk();
+        assert_eq!(chunk.total_size(), 54);
+        assert_eq!(chunk.total_size(), chunk.as_bytes().len());
+    }
+
+    #[test]
+    fn test_chunk_set_data() {
+        let mut chunk = testing_chunk();
+        chunk.set_data("Hello".as_bytes().to_vec());
+
+        let expected = Chunk::new(ChunkType::from_str("RuSt").unwrap(), "Hello".as_bytes().to_vec());
+        assert_eq!(chunk, expected);
+        assert_eq!(chunk.length(), 5);
+        assert_eq!(Chunk::try_from(chunk.as_bytes().as_ref()).unwrap(), chunk);
+    }
+
+    #[test]
+    fn test_chunk_length_is_big_endian() {
+        let chunk_type = ChunkType::from_str("RuSt").unwrap();
+        let chunk = Chunk::new(chunk_type, vec![0; 258]);
+        assert_eq!(chunk.as_bytes()[0..4], [0, 0, 1, 2]);
+    }
+
+    #[test]
+    fn test_chunk_crc_is_big_endian() {
+        let chunk = testing_chunk();
+        let bytes = chunk.as_bytes();
+        assert_eq!(bytes[bytes.len() - 4..], [0xab, 0xd1, 0xd8, 0x4e]);
+    }
+
+    #[test]
+    fn test_chunk_field_bytes() {
+        let chunk = Chunk::new(ChunkType::from_str("RuSt").unwrap(), vec![0; 258]);
+        assert_eq!(chunk.length_bytes(), [0, 0, 1, 2]);
+
+        let chunk = testing_chunk();
+        assert_eq!(chunk.crc_bytes(), [0xab, 0xd1, 0xd8, 0x4e]);
+        let bytes = chunk.as_bytes();
+        assert_eq!(bytes[..4], chunk.length_bytes());
+        assert_eq!(bytes[bytes.len() - 4..], chunk.crc_bytes());
+    }
+
+    #[test]
+    fn test_short_chunk_from_bytes() {
+        assert_eq!(Chunk::try_from(&[][..]), Err(ChunkError::BadLen));
+        assert_eq!(Chunk::try_from(&[0, 0, 0, 0, 82, 117, 83, 116, 1, 2, 3][..]), Err(ChunkError::BadLen));
+
+        // A length field claiming more data than the slice holds
+        let chunk_data = [0, 0, 0, 1, 82, 117, 83, 116, 1, 2, 3, 4];
+        assert_eq!(Chunk::try_from(&chunk_data[..]), Err(ChunkError::BadDataLen));
+    }
+
+    #[test]
+    fn test_chunk_length_too_large() {
+        let chunk_data = [0x80, 0, 0, 0, 82, 117, 83, 116, 1, 2, 3, 4];
+        assert_eq!(Chunk::try_from(&chunk_data[..]), Err(ChunkError::LengthTooLarge));
+        assert_eq!(Chunk::try_from_lenient(&chunk_data[..]), Err(ChunkError::LengthTooLarge));
+    }
+
     #[test]
     fn test_valid_chunk_from_bytes() {
         let data_length: u32 = 42;
@@ -224,6 +518,47 @@ mod tests {
         assert_eq!(chunk.as_bytes(), chunk_data);
     }
 
+    #[test]
+    fn test_chunk_as_bytes_matches_iterator_chain() {
+        let chunks = [
+            testing_chunk(),
+            Chunk::try_new("IEND", Vec::new()).unwrap(),
+            Chunk::try_new("IDAT", vec![0xab; 1000]).unwrap(),
+            Chunk::from_parts(ChunkType::from_str("ruSt").unwrap(), b"bad crc".to_vec(), 0),
+        ];
+        for chunk in chunks {
+            let expected: Vec<u8> = chunk.length()
+                .to_be_bytes()
+                .iter()
+                .chain(chunk.chunk_type().bytes().iter())
+                .chain(chunk.data().iter())
+                .chain(chunk.crc().to_be_bytes().iter())
+                .copied()
+                .collect();
+            assert_eq!(chunk.as_bytes(), expected);
+        }
+    }
+
+    #[test]
+    fn test_chunk_as_array() {
+        let iend = Chunk::try_new("IEND", Vec::new()).unwrap();
+        assert_eq!(iend.as_array::<12>(), Some([0, 0, 0, 0, b'I', b'E', b'N', b'D', 0xae, 0x42, 0x60, 0x82]));
+        assert_eq!(iend.as_array::<12>().unwrap().to_vec(), iend.as_bytes());
+
+        let chunk = testing_chunk();
+        assert_eq!(chunk.as_array::<54>().unwrap().to_vec(), chunk.as_bytes());
+        assert_eq!(chunk.as_array::<12>(), None);
+        assert_eq!(iend.as_array::<13>(), None);
+    }
+
+    #[test]
+    fn test_chunk_write_to() {
+        let chunk = testing_chunk();
+        let mut bytes = Vec::new();
+        chunk.write_to(&mut bytes).unwrap();
+        assert_eq!(bytes, chunk.as_bytes());
+    }
+
     #[test]
     fn test_invalid_chunk_from_bytes() {
         let data_length: u32 = 42;
@@ -245,6 +580,48 @@ mod tests {
         assert!(chunk.is_err());
     }
 
+    #[test]
+    fn test_lenient_chunk_from_bytes() {
+        let mut chunk_data = testing_chunk().as_bytes();
+        let crc_idx = chunk_data.len() - 1;
+        chunk_data[crc_idx] ^= 1;
+
+        assert_eq!(Chunk::try_from(chunk_data.as_ref()), Err(ChunkError::BadCrc));
+
+        let chunk = Chunk::try_from_lenient(chunk_data.as_ref()).unwrap();
+        assert!(!chunk.is_crc_valid());
+        assert_eq!(chunk.crc(), 2882656334 ^ 1);
+        assert_eq!(&chunk.data_as_string().unwrap(), "This is where your secret message will be!");
+        assert_eq!(chunk.as_bytes(), chunk_data);
+
+        assert!(testing_chunk().is_crc_valid());
+    }
+
+    #[test]
+    fn test_chunk_validate() {
+        assert_eq!(testing_chunk().validate(), Ok(()));
+
+        let chunk = Chunk::from_parts(ChunkType::from_str("RuSt").unwrap(), b"data".to_vec(), 0);
+        assert_eq!(chunk.validate(), Err(ChunkError::BadCrc));
+
+        let chunk = Chunk::new(ChunkType::from_str("Rust").unwrap(), b"data".to_vec());
+        assert_eq!(chunk.validate(), Err(ChunkError::ChunkType(ChunkTypeError::ReservedBitInvalid)));
+
+        let chunk = Chunk::from_parts(ChunkType::from_str("Rust").unwrap(), b"data".to_vec(), 0);
+        assert_eq!(chunk.validate(), Err(ChunkError::BadCrc));
+    }
+
+    #[test]
+    fn test_chunk_with_recomputed_crc() {
+        let mut chunk_data = testing_chunk().as_bytes();
+        let crc_idx = chunk_data.len() - 1;
+        chunk_data[crc_idx] ^= 1;
+
+        let chunk = Chunk::try_from_lenient(chunk_data.as_ref()).unwrap().with_recomputed_crc();
+        assert!(chunk.is_crc_valid());
+        assert_eq!(chunk, testing_chunk());
+    }
+
     #[test]
     pub fn test_chunk_trait_impls() {
         let data_length: u32 = 42;
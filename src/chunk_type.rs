@@ -1,4 +1,6 @@
-#[derive(Debug, PartialEq, Eq, Clone)]
+/// Chunk types are ordered by their ASCII bytes, so uppercase (critical)
+/// letters sort before lowercase ones.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone)]
 pub struct ChunkType([u8; 4]);
 
 use std::fmt::{Display, Formatter};
@@ -10,6 +12,8 @@ pub enum ChunkTypeError {
     // Unknown,
     ByteOutOfRange,
     BadLen,
+    ReservedBitInvalid,
+    NonAscii(char),
 }
 
 impl Display for ChunkTypeError {
@@ -18,6 +22,8 @@ impl Display for ChunkTypeError {
             // ChunkTypeError::Unknown => write!(f, "Some error happened!"),
             ChunkTypeError::ByteOutOfRange => write!(f, "Out of range byte encountered!"),
             ChunkTypeError::BadLen => write!(f, "Too few bytes to construct a Chunk Type"),
+            ChunkTypeError::ReservedBitInvalid => write!(f, "Reserved bit is set, third letter must be uppercase"),
+            ChunkTypeError::NonAscii(c) => write!(f, "Chunk type must be ASCII letters only; \"{}\" is not allowed", c),
         }
     }
 }
@@ -27,6 +33,12 @@ impl Error for ChunkTypeError {}
 impl ChunkType {
     const PROPERTY_BIT_MASK: u8 = 32u8;
 
+    pub const IHDR: ChunkType = ChunkType(*b"IHDR");
+    pub const PLTE: ChunkType = ChunkType(*b"PLTE");
+    pub const IDAT: ChunkType = ChunkType(*b"IDAT");
+    pub const IEND: ChunkType = ChunkType(*b"IEND");
+    pub(crate) const TEXT: ChunkType = ChunkType(*b"tEXt");
+
     pub fn bytes(&self) -> [u8; 4] {
         self.0.clone()
     }
@@ -39,10 +51,24 @@ impl ChunkType {
         self.0[0] & Self::PROPERTY_BIT_MASK == 0
     }
 
+    /// Whether this is one of the critical chunk types defined by the PNG
+    /// spec. Decoders must reject any other critical chunk.
+    pub fn is_known_critical(&self) -> bool {
+        [Self::IHDR, Self::PLTE, Self::IDAT, Self::IEND].contains(self)
+    }
+
+    pub fn is_ancillary(&self) -> bool {
+        !self.is_critical()
+    }
+
     pub fn is_public(&self) -> bool {
         self.0[1] & Self::PROPERTY_BIT_MASK == 0
     }
 
+    pub fn is_private(&self) -> bool {
+        !self.is_public()
+    }
+
     pub fn is_reserved_bit_valid(&self) -> bool {
         self.0[2] & Self::PROPERTY_BIT_MASK == 0
     }
@@ -50,6 +76,75 @@ impl ChunkType {
     pub fn is_safe_to_copy(&self) -> bool {
         self.0[3] & Self::PROPERTY_BIT_MASK != 0
     }
+
+    /// Spells out the four properties encoded in the case of the letters,
+    /// e.g. "Ancillary, private, reserved-bit valid, safe to copy".
+    pub fn describe(&self) -> String {
+        format!(
+            "{}, {}, reserved-bit {}, {} to copy",
+            if self.is_critical() { "Critical" } else { "Ancillary" },
+            if self.is_public() { "public" } else { "private" },
+            if self.is_reserved_bit_valid() { "valid" } else { "invalid" },
+            if self.is_safe_to_copy() { "safe" } else { "unsafe" },
+        )
+    }
+
+    /// Whether both types are made of the same letters, ignoring the case
+    /// that encodes their properties.
+    pub fn eq_ignore_case(&self, other: &ChunkType) -> bool {
+        self.0.eq_ignore_ascii_case(&other.0)
+    }
+
+    /// Matches the chunk type against a pattern in which `?` stands for
+    /// any single letter and `*` for any run of letters. The pattern is
+    /// compared against the literal ASCII bytes, so case matters.
+    pub fn matches_glob(&self, pattern: &str) -> bool {
+        glob_match(pattern.as_bytes(), &self.0)
+    }
+
+    /// Builds a chunk type from any four bytes, skipping the check that
+    /// they are ASCII letters. This is only meant for reading and showing
+    /// non-conformant files; `Display` escapes the bytes that are not
+    /// letters.
+    pub fn from_bytes_unchecked(bytes: [u8; 4]) -> ChunkType {
+        Self(bytes)
+    }
+
+    /// Generates a chunk type that is ancillary, private, has a valid
+    /// reserved bit and is safe to copy. The same seed always yields the
+    /// same chunk type.
+    pub fn random_ancillary(seed: u64) -> ChunkType {
+        let mut state = seed;
+        let mut bytes = [0u8; 4];
+        for b in bytes.iter_mut() {
+            *b = b'A' + (splitmix64(&mut state) % 26) as u8;
+        }
+        bytes[0] |= Self::PROPERTY_BIT_MASK;
+        bytes[1] |= Self::PROPERTY_BIT_MASK;
+        bytes[2] &= !Self::PROPERTY_BIT_MASK;
+        bytes[3] |= Self::PROPERTY_BIT_MASK;
+        Self(bytes)
+    }
+}
+
+fn glob_match(pattern: &[u8], bytes: &[u8]) -> bool {
+    match (pattern.first(), bytes.first()) {
+        (None, None) => true,
+        (Some(b'*'), _) => {
+            glob_match(&pattern[1..], bytes) || (!bytes.is_empty() && glob_match(pattern, &bytes[1..]))
+        }
+        (Some(b'?'), Some(_)) => glob_match(&pattern[1..], &bytes[1..]),
+        (Some(p), Some(b)) => p == b && glob_match(&pattern[1..], &bytes[1..]),
+        _ => false,
+    }
+}
+
+fn splitmix64(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
 }
 
 impl TryFrom<[u8; 4]> for ChunkType {
@@ -81,16 +176,31 @@ impl TryFrom<&[u8]> for ChunkType {
 }
 
 
+impl TryFrom<&str> for ChunkType {
+    type Error = ChunkTypeError;
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        // Without this, a multibyte character only shows up as a bad length
+        if let Some(c) = s.chars().find(|c| !c.is_ascii()) {
+            return Err(ChunkTypeError::NonAscii(c));
+        }
+        s.as_bytes().try_into()
+    }
+}
+
 impl FromStr for ChunkType {
     type Err = ChunkTypeError;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        s.as_bytes().try_into()
+        Self::try_from(s)
     }
 }
 
 impl Display for ChunkType {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", std::str::from_utf8(&self.0).unwrap())
+        // Only types built with `from_bytes_unchecked` need escaping
+        for b in self.0 {
+            write!(f, "{}", std::ascii::escape_default(b))?;
+        }
+        Ok(())
     }
 }
 
@@ -127,6 +237,17 @@ mod tests {
         assert!(!chunk.is_critical());
     }
 
+    #[test]
+    pub fn test_chunk_type_is_ancillary() {
+        let chunk = ChunkType::from_str("ruSt").unwrap();
+        assert!(chunk.is_ancillary());
+        assert!(!chunk.is_critical());
+
+        let chunk = ChunkType::from_str("RuSt").unwrap();
+        assert!(!chunk.is_ancillary());
+        assert!(chunk.is_critical());
+    }
+
     #[test]
     pub fn test_chunk_type_is_public() {
         let chunk = ChunkType::from_str("RUSt").unwrap();
@@ -139,6 +260,17 @@ mod tests {
         assert!(!chunk.is_public());
     }
 
+    #[test]
+    pub fn test_chunk_type_is_private() {
+        let chunk = ChunkType::from_str("RuSt").unwrap();
+        assert!(chunk.is_private());
+        assert!(!chunk.is_public());
+
+        let chunk = ChunkType::from_str("RUSt").unwrap();
+        assert!(!chunk.is_private());
+        assert!(chunk.is_public());
+    }
+
     #[test]
     pub fn test_chunk_type_is_reserved_bit_valid() {
         let chunk = ChunkType::from_str("RuSt").unwrap();
@@ -178,12 +310,138 @@ mod tests {
         assert!(chunk.is_err());
     }
 
+    #[test]
+    pub fn test_chunk_type_try_from_str() {
+        let expected = ChunkType::from_str("RuSt").unwrap();
+        assert_eq!(ChunkType::try_from("RuSt"), Ok(expected));
+        assert_eq!(ChunkType::try_from("RuS"), Err(ChunkTypeError::BadLen));
+        assert_eq!(ChunkType::try_from("RuStS"), Err(ChunkTypeError::BadLen));
+        assert_eq!(ChunkType::try_from("ruSé"), Err(ChunkTypeError::NonAscii('é')));
+    }
+
+    #[test]
+    pub fn test_chunk_type_from_non_ascii_str() {
+        assert!(ChunkType::from_str("ruSt").is_ok());
+        assert_eq!(ChunkType::from_str("ruSé"), Err(ChunkTypeError::NonAscii('é')));
+        assert_eq!(ChunkType::from_str("ruSté"), Err(ChunkTypeError::NonAscii('é')));
+        assert_eq!(ChunkType::from_str("r€"), Err(ChunkTypeError::NonAscii('€')));
+        assert_eq!(ChunkType::from_str("ruS1"), Err(ChunkTypeError::ByteOutOfRange));
+        assert_eq!(
+            ChunkTypeError::NonAscii('é').to_string(),
+            "Chunk type must be ASCII letters only; \"é\" is not allowed"
+        );
+    }
+
     #[test]
     pub fn test_chunk_type_string() {
         let chunk = ChunkType::from_str("RuSt").unwrap();
         assert_eq!(&chunk.to_string(), "RuSt");
     }
 
+    #[test]
+    pub fn test_critical_chunk_type_constants() {
+        for (constant, name) in [
+            (ChunkType::IHDR, "IHDR"),
+            (ChunkType::PLTE, "PLTE"),
+            (ChunkType::IDAT, "IDAT"),
+            (ChunkType::IEND, "IEND"),
+        ] {
+            assert_eq!(constant, ChunkType::from_str(name).unwrap());
+            assert!(constant.is_critical());
+            assert!(constant.is_valid());
+        }
+    }
+
+    #[test]
+    pub fn test_chunk_type_is_known_critical() {
+        assert!(ChunkType::IDAT.is_known_critical());
+        assert!(!ChunkType::from_str("RuSt").unwrap().is_known_critical());
+        assert!(!ChunkType::from_str("tEXt").unwrap().is_known_critical());
+    }
+
+    #[test]
+    pub fn test_chunk_type_from_bytes_unchecked() {
+        assert_eq!(ChunkType::try_from([0x01, 0x02, 0x03, 0x04]), Err(ChunkTypeError::ByteOutOfRange));
+
+        let chunk_type = ChunkType::from_bytes_unchecked([0x01, 0x02, 0x03, 0x04]);
+        assert_eq!(chunk_type.bytes(), [0x01, 0x02, 0x03, 0x04]);
+        assert_eq!(chunk_type.to_string(), "\\x01\\x02\\x03\\x04");
+        assert_eq!(chunk_type.describe(), "Critical, public, reserved-bit valid, unsafe to copy");
+
+        let chunk_type = ChunkType::from_bytes_unchecked([b'r', 0xff, b'"', b'\n']);
+        assert_eq!(chunk_type.to_string(), "r\\xff\\\"\\n");
+    }
+
+    #[test]
+    pub fn test_chunk_type_describe() {
+        assert_eq!(
+            ChunkType::from_str("RuSt").unwrap().describe(),
+            "Critical, private, reserved-bit valid, safe to copy"
+        );
+        assert_eq!(ChunkType::IHDR.describe(), "Critical, public, reserved-bit valid, unsafe to copy");
+        assert_eq!(
+            ChunkType::from_str("ruSt").unwrap().describe(),
+            "Ancillary, private, reserved-bit valid, safe to copy"
+        );
+        assert_eq!(
+            ChunkType::from_str("Rust").unwrap().describe(),
+            "Critical, private, reserved-bit invalid, safe to copy"
+        );
+    }
+
+    #[test]
+    pub fn test_chunk_type_eq_ignore_case() {
+        let chunk_type = ChunkType::from_str("ruSt").unwrap();
+        assert!(chunk_type.eq_ignore_case(&ChunkType::from_str("RuSt").unwrap()));
+        assert!(chunk_type.eq_ignore_case(&ChunkType::from_str("RUST").unwrap()));
+        assert!(!chunk_type.eq_ignore_case(&ChunkType::from_str("ruSx").unwrap()));
+    }
+
+    #[test]
+    pub fn test_chunk_type_matches_glob() {
+        let chunk = ChunkType::from_str("tEXt").unwrap();
+        assert!(chunk.matches_glob("tEXt"));
+        assert!(chunk.matches_glob("tE*"));
+        assert!(chunk.matches_glob("*"));
+        assert!(chunk.matches_glob("??Xt"));
+        assert!(chunk.matches_glob("t*t"));
+        assert!(chunk.matches_glob("*X*"));
+        assert!(!chunk.matches_glob("te*"));
+        assert!(!chunk.matches_glob("???"));
+        assert!(!chunk.matches_glob("?????"));
+        assert!(!chunk.matches_glob("*Z"));
+        assert!(!chunk.matches_glob(""));
+    }
+
+    #[test]
+    pub fn test_chunk_type_ordering() {
+        let mut chunk_types: Vec<ChunkType> = ["tEXt", "IEND", "IHDR", "gAMA", "IDAT"]
+            .iter()
+            .map(|s| ChunkType::from_str(s).unwrap())
+            .collect();
+        chunk_types.sort();
+        let names: Vec<String> = chunk_types.iter().map(|c| c.to_string()).collect();
+        assert_eq!(names, ["IDAT", "IEND", "IHDR", "gAMA", "tEXt"]);
+    }
+
+    #[test]
+    pub fn test_random_ancillary_chunk_type() {
+        for seed in 0..64 {
+            let chunk = ChunkType::random_ancillary(seed);
+            assert!(ChunkType::try_from(chunk.bytes()).is_ok());
+            assert!(!chunk.is_critical());
+            assert!(!chunk.is_public());
+            assert!(chunk.is_reserved_bit_valid());
+            assert!(chunk.is_safe_to_copy());
+        }
+    }
+
+    #[test]
+    pub fn test_random_ancillary_chunk_type_is_reproducible() {
+        assert_eq!(ChunkType::random_ancillary(42), ChunkType::random_ancillary(42));
+        assert_ne!(ChunkType::random_ancillary(42), ChunkType::random_ancillary(43));
+    }
+
     #[test]
     pub fn test_chunk_type_trait_impls() {
         let chunk_type_1: ChunkType = TryFrom::try_from([82, 117, 83, 116]).unwrap();
@@ -1,54 +1,1342 @@
+use std::borrow::Cow;
 use std::error::Error;
 use std::str::FromStr;
-use std::fs;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, IsTerminal, Read, Write};
+use std::path::Path;
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
-use crate::chunk_type::ChunkType;
+use crc::{Crc, CRC_32_ISO_HDLC};
+
+use crate::ancillary;
+use crate::chunk_type::{ChunkType,ChunkTypeError};
 use crate::chunk::Chunk;
-use crate::png::{Png,PngError};
+use crate::crypto;
+use crate::ihdr::Ihdr;
+use crate::png::{DuplicateKeep,Png,PngError,PngParser};
 
-use crate::args::{EncodeArgs,DecodeArgs,RemoveArgs,PrintArgs};
+use crate::args::{EncodeArgs,DecodeArgs,RemoveArgs,PrintArgs,ExportArgs,ImportArgs,ChecksumArgs,ChecksumAlgorithm,SanitizeArgs,RenameArgs,SetTextArgs,CanonicalizeArgs,DedupArgs,DedupeKeep,StripTrailingArgs,WatchArgs,BatchEncodeArgs,InputFormat,PrintFormat,Base};
 
-pub fn encode(args: &EncodeArgs) -> Result<(), Box<dyn Error>> {
-    let data = fs::read(&args.input_file_path)?;
-    let mut png = Png::try_from(data.as_ref())?;
+/// Describes a step on stderr when `--verbose` is given, leaving stdout
+/// to the command's output.
+macro_rules! trace {
+    ($verbose:expr, $($arg:tt)*) => {
+        if $verbose {
+            eprintln!($($arg)*);
+        }
+    };
+}
 
-    let end_chunk = png.remove_chunk("IEND")?;
-    let chunk_type = ChunkType::from_str(&args.chunk_type_str)?;
-    let new_chunk = Chunk::new(chunk_type, args.message.clone().into_bytes());
-    png.append_chunk(new_chunk);
-    png.append_chunk(end_chunk);
+pub fn encode(args: &EncodeArgs, verbose: bool) -> Result<(), Box<dyn Error>> {
+    let [input_file_path, chunk_type_str, message, output_file_path] = assign_positionals(
+        [
+            args.input_file_path.as_deref(),
+            args.chunk_type_str.as_deref(),
+            args.message.as_deref(),
+            args.output_file_path.as_deref(),
+        ],
+        [args.pipe, args.random_type, false, args.pipe],
+    )?;
+    let message = message.ok_or("Missing message argument")?;
+
+    let derived_path = match (args.copy, input_file_path) {
+        (true, Some(input)) => Some(copy_path(input)),
+        _ => None,
+    };
+    let output_file_path = output_file_path.or(derived_path.as_deref());
+    if let (true, Some(input), Some(output)) = (args.copy, input_file_path, output_file_path) {
+        if input == output || is_same_file(input, output) {
+            return Err("--copy refuses to overwrite the input file".into());
+        }
+    }
+
+    let data = read_input(input_file_path, verbose)?;
+    let mut png = Png::from_bytes(&data).map_err(hint_extension(input_file_path))?;
+    trace!(verbose, "parsed {} chunks", png.chunks().len());
+
+    let chunk_type = if args.random_type {
+        let seed = match args.seed {
+            Some(seed) => seed,
+            None => SystemTime::now().duration_since(UNIX_EPOCH)?.as_nanos() as u64,
+        };
+        let chunk_type = ChunkType::random_ancillary(seed);
+        if args.pipe {
+            eprintln!("{}", chunk_type);
+        } else {
+            println!("{}", chunk_type);
+        }
+        chunk_type
+    } else {
+        ChunkType::from_str(chunk_type_str.ok_or("Missing chunk type argument")?)?
+    };
+    if !chunk_type.is_valid() {
+        if args.strict {
+            return Err(ChunkTypeError::ReservedBitInvalid.into());
+        }
+        eprintln!("Warning: chunk type {} has its reserved bit set, the output does not conform to the PNG spec", chunk_type);
+    }
+    let message = if args.encrypt {
+        crypto::encrypt(message.as_bytes(), args.passphrase.as_deref().ok_or("Missing passphrase")?)?
+    } else {
+        message.as_bytes().to_vec()
+    };
+    let new_chunk = Chunk::new(chunk_type, message);
+    let description = format!("{} ({} bytes)", new_chunk.chunk_type(), new_chunk.length());
+    let replaced = args.upsert
+        && png.replace_chunk_data_by_type(&new_chunk.chunk_type().to_string(), new_chunk.data().to_vec()).is_ok();
+    if replaced {
+        trace!(verbose, "replaced the data of {}", description);
+    } else if args.before_idat {
+        png.insert_before_type(&ChunkType::IDAT.to_string(), new_chunk).map_err(|_| "No IDAT chunk found")?;
+        trace!(verbose, "inserted {} before IDAT", description);
+    } else if args.no_iend_move {
+        png.append_chunk(new_chunk);
+        trace!(verbose, "appended {}", description);
+    } else if png.insert_chunk_sorted(new_chunk) {
+        trace!(verbose, "inserted {}", description);
+    } else {
+        eprintln!("Warning: no IEND chunk found, appending the chunk at the end");
+    }
+
+    let path = output_path(input_file_path, output_file_path);
+    if args.write_if_changed && is_unchanged(input_file_path, path, &png, &data) {
+        trace!(verbose, "{} is unchanged, not writing it", path.unwrap_or("stdout"));
+        return Ok(());
+    }
+    write_png(path, &png, verbose)?;
+    Ok(())
+}
+
+/// Lines the optional positional arguments up with the slots they stand
+/// for. Slots already provided by a flag (e.g. `--random-type` provides
+/// the chunk type) are skipped, so the values given shift into the next
+/// free slot instead.
+fn assign_positionals<const N: usize>(
+    values: [Option<&str>; N],
+    provided: [bool; N],
+) -> Result<[Option<&str>; N], Box<dyn Error>> {
+    let mut values = values.into_iter().flatten();
+    let mut res = [None; N];
+    for (slot, provided) in res.iter_mut().zip(provided) {
+        if !provided {
+            *slot = values.next();
+        }
+    }
+    if let Some(extra) = values.next() {
+        return Err(format!("Unexpected argument '{}'", extra).into());
+    }
+    Ok(res)
+}
+
+/// Reads the input file, or all of stdin if there is no path.
+fn read_input(path: Option<&str>, verbose: bool) -> io::Result<Vec<u8>> {
+    let data = match path {
+        Some(path) => fs::read(path)?,
+        None => {
+            let mut data = Vec::new();
+            io::stdin().lock().read_to_end(&mut data)?;
+            data
+        }
+    };
+    trace!(verbose, "read {} bytes from {}", data.len(), path.unwrap_or("stdin"));
+    Ok(data)
+}
+
+/// Bytes of an input file for commands that only read it. With the `mmap`
+/// feature the file is memory-mapped rather than copied onto the heap, which
+/// matters for multi-gigabyte files where only a small chunk is wanted.
+enum Input {
+    Owned(Vec<u8>),
+    #[cfg(feature = "mmap")]
+    Mapped(memmap2::Mmap),
+}
+
+impl AsRef<[u8]> for Input {
+    fn as_ref(&self) -> &[u8] {
+        match self {
+            Input::Owned(data) => data,
+            #[cfg(feature = "mmap")]
+            Input::Mapped(map) => map,
+        }
+    }
+}
+
+#[cfg(feature = "mmap")]
+fn map_input(path: &str, verbose: bool) -> io::Result<Input> {
+    let file = File::open(path)?;
+    // Safety: the map is only read, and only for the lifetime of the
+    // command. Another process truncating the file underneath us is
+    // accepted as undefined, as with any mmap-based tool.
+    let map = unsafe { memmap2::Mmap::map(&file)? };
+    trace!(verbose, "mapped {} bytes from {}", map.len(), path);
+    Ok(Input::Mapped(map))
+}
+
+#[cfg(not(feature = "mmap"))]
+fn map_input(path: &str, verbose: bool) -> io::Result<Input> {
+    read_input(Some(path), verbose).map(Input::Owned)
+}
+
+/// Opens an input file for a read-only command, showing how much of it
+/// has been read when `progress` is set and stderr is a terminal.
+fn open_input(path: &str, progress: bool, verbose: bool) -> io::Result<Input> {
+    if progress && io::stderr().is_terminal() {
+        read_with_progress(path, verbose).map(Input::Owned)
+    } else {
+        map_input(path, verbose)
+    }
+}
+
+/// Reads the file in blocks, updating a percentage on stderr after each.
+fn read_with_progress(path: &str, verbose: bool) -> io::Result<Vec<u8>> {
+    const BLOCK_BYTES: usize = 1 << 20;
+
+    let mut file = File::open(path)?;
+    let total = file.metadata()?.len();
+    let mut data = Vec::with_capacity(total as usize);
+    let mut block = vec![0; BLOCK_BYTES];
+    loop {
+        let n = file.read(&mut block)?;
+        if n == 0 {
+            break;
+        }
+        data.extend_from_slice(&block[..n]);
+        eprint!("\rReading {}: {}%", path, percent(data.len() as u64, total));
+    }
+    eprintln!();
+    trace!(verbose, "read {} bytes from {}", data.len(), path);
+    Ok(data)
+}
+
+/// `done` as a percentage of `total`, capped at 100 in case the file grew
+/// while being read.
+fn percent(done: u64, total: u64) -> u64 {
+    if total == 0 {
+        return 100;
+    }
+    (done.saturating_mul(100) / total).min(100)
+}
+
+/// Adds a note to signature errors when the input file does not have a
+/// `.png` extension, as it is then most likely not a PNG at all.
+fn hint_extension(path: Option<&str>) -> impl Fn(PngError) -> Box<dyn Error> + '_ {
+    move |err| {
+        let ext = path.and_then(|p| Path::new(p).extension());
+        match (&err, ext) {
+            (PngError::BadHeader | PngError::BadSignature(_), Some(ext)) if !ext.eq_ignore_ascii_case("png") => {
+                format!("{}\nnote: file extension is .{}; is this actually a PNG?", err, ext.to_string_lossy()).into()
+            }
+            _ => err.into(),
+        }
+    }
+}
+
+/// The output path `--copy` derives from the input, e.g. `in.embedded.png`
+/// for `in.png`.
+fn copy_path(input: &str) -> String {
+    Path::new(input).with_extension("embedded.png").to_string_lossy().into_owned()
+}
+
+/// Picks the path the result is written to. An output path naming the
+/// same file as the input, e.g. `./in.png` for `in.png`, is an in-place
+/// edit of the input rather than a separate output.
+fn output_path<'a>(input: Option<&'a str>, output: Option<&'a str>) -> Option<&'a str> {
+    match (input, output) {
+        (Some(input), Some(output)) if is_same_file(input, output) => Some(input),
+        (input, output) => output.or(input),
+    }
+}
+
+fn is_same_file(a: &str, b: &str) -> bool {
+    match (fs::canonicalize(a), fs::canonicalize(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    }
+}
+
+/// Whether writing the PNG to `path` would only rewrite the input file
+/// with the bytes it already holds, which `--write-if-changed` skips.
+fn is_unchanged(input: Option<&str>, path: Option<&str>, png: &Png, data: &[u8]) -> bool {
+    input.is_some() && path == input && png.as_bytes() == data
+}
+
+/// Writes the PNG to the given path, or to stdout if there is no path.
+fn write_png(path: Option<&str>, png: &Png, verbose: bool) -> io::Result<()> {
+    let out: Box<dyn Write> = match path {
+        Some(path) => Box::new(File::create(path)?),
+        None => Box::new(io::stdout().lock()),
+    };
+    let mut writer = BufWriter::new(out);
+    png.write_to_writer(&mut writer)?;
+    writer.flush()?;
+    trace!(verbose, "wrote {} bytes to {}", png.total_size(), path.unwrap_or("stdout"));
+    Ok(())
+}
+
+/// Returns the output of the command, i.e. the decoded messages each
+/// followed by the delimiter, or the trailing bytes. Nothing is printed
+/// to stdout, which is left to the caller. With `--exists` the output is
+/// empty and `PngError::ChunkNotFound` is returned when nothing matches.
+pub fn decode(args: &DecodeArgs, verbose: bool) -> Result<Vec<u8>, Box<dyn Error>> {
+    let data = open_input(&args.input_file_path, args.progress, verbose)?;
+    let mut parser = parser_for(args.input_format).check_crc(!args.ignore_crc);
+    if let Some(max_chunks) = args.max_chunks {
+        parser = parser.max_chunks(max_chunks);
+    }
+    let png = parser.parse(data.as_ref()).map_err(hint_extension(Some(&args.input_file_path)))?;
+    trace!(verbose, "parsed {} chunks", png.chunks().len());
+
+    if args.trailing {
+        if png.trailing_bytes().is_empty() {
+            return Err("No trailing data, the file ends cleanly at IEND".into());
+        }
+        return match &args.output {
+            Some(path) => {
+                fs::write(path, png.trailing_bytes())?;
+                Ok(Vec::new())
+            }
+            None => Ok(png.trailing_bytes().to_vec()),
+        };
+    }
+
+    let mut indices = select_chunks(&png, args.chunk_type_str.as_deref(), args.glob.as_deref())?;
+    if args.exists {
+        if indices.is_empty() {
+            return Err(PngError::ChunkNotFound.into());
+        }
+        return Ok(Vec::new());
+    }
+
+    if let Some(expected) = args.expect_count {
+        check_count(expected, indices.len())?;
+    }
+    if args.last {
+        indices.drain(..indices.len().saturating_sub(1));
+    } else if !args.all {
+        indices.truncate(1);
+    }
+    if indices.is_empty() {
+        return Err(PngError::ChunkNotFound.into());
+    }
+
+    let delimiter = unescape(&args.delimiter);
+    let mut output = Vec::new();
+    for idx in indices {
+        let chunk = &png.chunks()[idx];
+        if !chunk.is_crc_valid() {
+            eprintln!("Warning: CRC mismatch (data may be corrupt)");
+        }
+        let chunk = if args.decrypt {
+            let data = crypto::decrypt(chunk.data(), args.passphrase.as_deref().ok_or("Missing passphrase")?)?;
+            Cow::Owned(Chunk::new(chunk.chunk_type().clone(), data))
+        } else {
+            Cow::Borrowed(chunk)
+        };
+        trace!(verbose, "decoded {} ({} bytes)", chunk.chunk_type(), chunk.length());
+        let chunk_data = if args.lossy {
+            chunk.data_as_string_lossy()
+        } else {
+            chunk.data_as_string()?
+        };
+        let chunk_data = match args.max_chars {
+            Some(max_chars) => truncate_chars(&chunk_data, max_chars),
+            None => Cow::Borrowed(chunk_data.as_str()),
+        };
+        output.extend_from_slice(chunk_data.as_bytes());
+        output.extend_from_slice(delimiter.as_bytes());
+    }
+    Ok(output)
+}
+
+/// Cuts `s` after `max_chars` characters and marks the cut with an
+/// ellipsis. The cut is made on a char boundary, never inside a multibyte
+/// sequence.
+fn truncate_chars(s: &str, max_chars: usize) -> Cow<'_, str> {
+    match s.char_indices().nth(max_chars) {
+        Some((idx, _)) => Cow::Owned(format!("{}…", &s[..idx])),
+        None => Cow::Borrowed(s),
+    }
+}
+
+/// Fails unless exactly `expected` chunks matched, so that a script does
+/// not silently decode too few or too many messages.
+fn check_count(expected: usize, actual: usize) -> Result<(), Box<dyn Error>> {
+    if expected != actual {
+        return Err(format!("Expected {} matching chunks, found {}", expected, actual).into());
+    }
+    Ok(())
+}
+
+/// Picks the parser for the input format. PNG is the only format so far,
+/// so `auto` resolves to it.
+fn parser_for(format: InputFormat) -> PngParser {
+    match format {
+        InputFormat::Auto | InputFormat::Png => Png::parser(),
+    }
+}
+
+/// Indices of the chunks selected either by a `--glob` pattern or by an
+/// exact chunk type.
+fn select_chunks(png: &Png, chunk_type_str: Option<&str>, glob: Option<&str>) -> Result<Vec<usize>, Box<dyn Error>> {
+    match (glob, chunk_type_str) {
+        (Some(pattern), _) => Ok(png.chunks().iter()
+            .enumerate()
+            .filter(|(_, c)| c.chunk_type().matches_glob(pattern))
+            .map(|(idx, _)| idx)
+            .collect()),
+        (None, Some(chunk_type_str)) => Ok(png.indices_of(chunk_type_str)),
+        (None, None) => Err("Missing chunk type argument".into()),
+    }
+}
+
+/// Expands the `\n`, `\t`, `\0` and `\\` escapes so that separators
+/// which are awkward to type in a shell can still be passed as arguments.
+fn unescape(s: &str) -> String {
+    let mut res = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            res.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => res.push('\n'),
+            Some('t') => res.push('\t'),
+            Some('0') => res.push('\0'),
+            Some('\\') => res.push('\\'),
+            Some(other) => {
+                res.push('\\');
+                res.push(other);
+            }
+            None => res.push('\\'),
+        }
+    }
+    res
+}
 
-    if args.output_file_path.is_some() {
-        fs::write(args.output_file_path.as_ref().unwrap(), png.as_bytes())?;
+pub fn remove(args: &RemoveArgs, verbose: bool) -> Result<(), Box<dyn Error>> {
+    let [input_file_path, chunk_type_str] = assign_positionals(
+        [args.input_file_path.as_deref(), args.chunk_type_str.as_deref()],
+        [args.pipe, args.glob.is_some()],
+    )?;
+
+    let data = read_input(input_file_path, verbose)?;
+    let mut png = Png::from_bytes(&data).map_err(hint_extension(input_file_path))?;
+    trace!(verbose, "parsed {} chunks", png.chunks().len());
+
+    if let Some(pattern) = &args.glob {
+        let removed = remove_where(&mut png, |c| c.chunk_type().matches_glob(pattern));
+        if removed == 0 && !args.if_present {
+            return Err(PngError::ChunkNotFound.into());
+        }
+        trace!(verbose, "removed {} chunks matching {}", removed, pattern);
+    } else if args.normalize_case {
+        let chunk_type = ChunkType::from_str(chunk_type_str.ok_or("Missing chunk type argument")?)?;
+        let removed = remove_where(&mut png, |c| c.chunk_type().eq_ignore_case(&chunk_type));
+        if removed == 0 && !args.if_present {
+            return Err(PngError::ChunkNotFound.into());
+        }
+        trace!(verbose, "removed {} chunks matching {} in any case", removed, chunk_type);
     } else {
-        fs::write(&args.input_file_path, png.as_bytes())?;
+        let chunk_type_str = chunk_type_str.ok_or("Missing chunk type argument")?;
+        let chunk = if args.if_present {
+            // A misspelled type must still be reported rather than ignored
+            ChunkType::from_str(chunk_type_str)?;
+            png.remove_chunk_if_present(chunk_type_str)
+        } else {
+            Some(png.remove_chunk(chunk_type_str)?)
+        };
+        match chunk {
+            Some(chunk) => trace!(verbose, "removed {} ({} bytes)", chunk.chunk_type(), chunk.length()),
+            None => trace!(verbose, "no {} chunk to remove", chunk_type_str),
+        }
+    }
+    if args.write_if_changed && is_unchanged(input_file_path, input_file_path, &png, &data) {
+        trace!(verbose, "{} is unchanged, not writing it", input_file_path.unwrap_or("stdout"));
+        return Ok(());
     }
+    write_png(input_file_path, &png, verbose)?;
     Ok(())
 }
 
-pub fn decode(args: &DecodeArgs) -> Result<(), Box<dyn Error>> {
-    let data = fs::read(&args.input_file_path)?;
-    let png = Png::try_from(data.as_ref())?;
+/// Removes every chunk for which `f` returns true, keeping the trailing
+/// bytes, and returns how many chunks were removed.
+fn remove_where(png: &mut Png, f: impl Fn(&Chunk) -> bool) -> usize {
+    let old = std::mem::replace(png, Png::from_chunks(Vec::new()));
+    let num_chunks = old.chunks().len();
+    let trailing = old.trailing_bytes().to_vec();
+    *png = Png::from_chunks(old.into_chunks().into_iter().filter(|c| !f(c)).collect());
+    png.set_trailing_bytes(trailing);
+    num_chunks - png.chunks().len()
+}
+
+pub fn print(args: &PrintArgs, verbose: bool) -> Result<(), Box<dyn Error>> {
+    let data = open_input(&args.input_file_path, args.progress, verbose)?;
+    let mut parser = parser_for(args.input_format)
+        .check_crc(!args.check_crc && !args.no_crc_check)
+        .allow_non_ascii_type(args.allow_non_ascii_type);
+    if let Some(max_chunks) = args.max_chunks {
+        parser = parser.max_chunks(max_chunks);
+    }
+    let png = if args.best_effort {
+        let (png, err) = parser.parse_partial(data.as_ref());
+        if let Some(e) = err {
+            eprintln!("Warning: stopped reading after {} chunks: {}", png.chunks().len(), e);
+        }
+        png
+    } else {
+        parser.parse(data.as_ref()).map_err(hint_extension(Some(&args.input_file_path)))?
+    };
+    trace!(verbose, "parsed {} chunks", png.chunks().len());
+
+    if args.show_invalid {
+        let num_chunks = png.chunks().len();
+        for (idx, chunk) in png.chunks().iter().enumerate() {
+            if let Some(reason) = invalid_reason(chunk.chunk_type()) {
+                println!("* CHUNK #[{:03}/{:03}]: {}: {}", idx + 1, num_chunks, chunk.chunk_type(), reason);
+            }
+        }
+        return Ok(());
+    }
+
+    if args.pretty_hex {
+        print!("{}", png.hex_dump());
+        return Ok(());
+    }
+
+    if args.info && args.format == PrintFormat::Text {
+        let chunk = png.chunk_by_type(&ChunkType::IHDR.to_string()).ok_or(PngError::ChunkNotFound)?;
+        println!("IHDR: {}", Ihdr::try_from(chunk)?);
+        if let Some(summary) = idat_summary(&png) {
+            println!("IDAT: {}", summary);
+        }
+        for chunk in png.chunks() {
+            if let Some(description) = ancillary::describe(chunk) {
+                println!("{}: {}", chunk.chunk_type(), description);
+            }
+        }
+    }
+    let png = match &args.glob {
+        Some(pattern) => Png::from_chunks(png.into_chunks()
+            .into_iter()
+            .filter(|c| c.chunk_type().matches_glob(pattern))
+            .collect()),
+        None => png,
+    };
+    let png = if args.sort {
+        let mut chunks = png.into_chunks();
+        chunks.sort_by(|a, b| a.chunk_type().cmp(b.chunk_type()));
+        Png::from_chunks(chunks)
+    } else {
+        png
+    };
+    if args.format == PrintFormat::Json {
+        println!("{}", json_report(png.chunks(), args.check_crc));
+        return Ok(());
+    }
+    if args.format == PrintFormat::Raw {
+        let mut writer = BufWriter::new(io::stdout().lock());
+        write_raw_records(png.chunks(), &mut writer)?;
+        writer.flush()?;
+        return Ok(());
+    }
+    let mut formatter = png.formatter()
+        .verbose(!args.brief)
+        .color(args.color)
+        .check_crc(args.check_crc)
+        .decimal(args.base == Base::Dec)
+        .explain(args.explain)
+        .index(args.index);
+    if let Some(limit) = args.limit {
+        formatter = formatter.limit(limit);
+    }
+    println!("{}", formatter);
+    if args.count {
+        let total_size: usize = png.chunks().iter().map(|c| c.total_size()).sum();
+        let total_size = if args.human {
+            format_size(total_size)
+        } else {
+            format!("{} bytes", total_size)
+        };
+        println!("Total: {} chunks, {}", png.chunks().len(), total_size);
+    }
+    Ok(())
+}
+
+/// Describes the IDAT chunks as a whole, as image data is often split
+/// across many of them.
+fn idat_summary(png: &Png) -> Option<String> {
+    let count = png.chunks().iter().filter(|c| *c.chunk_type() == ChunkType::IDAT).count();
+    if count == 0 {
+        return None;
+    }
+    Some(format!(
+        "{} chunk{}, {} bytes total (image data)",
+        count,
+        if count == 1 { "" } else { "s" },
+        group_thousands(png.idat_len()),
+    ))
+}
+
+/// Formats `n` with a comma between each group of three digits.
+fn group_thousands(n: u64) -> String {
+    let digits = n.to_string();
+    let mut grouped = String::with_capacity(digits.len() + digits.len() / 3);
+    for (idx, digit) in digits.chars().enumerate() {
+        if idx > 0 && (digits.len() - idx).is_multiple_of(3) {
+            grouped.push(',');
+        }
+        grouped.push(digit);
+    }
+    grouped
+}
+
+/// Describes the chunks as a JSON object, with `crc_valid` telling for each
+/// chunk whether its stored CRC matches a freshly computed one if
+/// `check_crc` is set.
+fn json_report(chunks: &[Chunk], check_crc: bool) -> String {
+    let chunks: Vec<String> = chunks.iter()
+        .map(|chunk| {
+            let crc_valid = if check_crc {
+                format!(",\"crc_valid\":{}", chunk.is_crc_valid())
+            } else {
+                String::new()
+            };
+            let data: String = chunk.data().iter().map(|b| format!("{:02x}", b)).collect();
+            format!(
+                "{{\"type\":{},\"length\":{},\"crc\":{}{},\"data\":\"{}\"}}",
+                json_string(&chunk.chunk_type().to_string()),
+                chunk.length(),
+                chunk.crc(),
+                crc_valid,
+                data,
+            )
+        })
+        .collect();
+    format!("{{\"chunks\":[{}]}}", chunks.join(","))
+}
+
+/// Quotes `s` as a JSON string.
+fn json_string(s: &str) -> String {
+    let mut res = String::with_capacity(s.len() + 2);
+    res.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => res.push_str("\\\""),
+            '\\' => res.push_str("\\\\"),
+            c if c.is_control() => res.push_str(&format!("\\u{:04x}", c as u32)),
+            c => res.push(c),
+        }
+    }
+    res.push('"');
+    res
+}
+
+/// Writes one record per chunk: the 4-byte chunk type, the data length as
+/// a 4-byte big-endian integer, then the data. Unlike a serialized chunk
+/// the type comes first and there is no CRC.
+fn write_raw_records(chunks: &[Chunk], w: &mut impl Write) -> io::Result<()> {
+    for chunk in chunks {
+        w.write_all(&chunk.chunk_type().bytes())?;
+        w.write_all(&chunk.length_bytes())?;
+        w.write_all(chunk.data())?;
+    }
+    Ok(())
+}
+
+/// Why a chunk type does not conform to the PNG spec, if it does not.
+fn invalid_reason(chunk_type: &ChunkType) -> Option<&'static str> {
+    if !chunk_type.is_valid() {
+        Some("reserved bit is set")
+    } else if chunk_type.is_critical() && !chunk_type.is_known_critical() {
+        Some("unknown critical chunk")
+    } else {
+        None
+    }
+}
+
+/// Formats a byte count using binary units, e.g. `2.1 MiB`.
+fn format_size(bytes: usize) -> String {
+    const UNITS: [&str; 5] = ["KiB", "MiB", "GiB", "TiB", "PiB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, UNITS[unit])
+}
+
+pub fn export(args: &ExportArgs, verbose: bool) -> Result<(), Box<dyn Error>> {
+    let data = read_input(Some(&args.input_file_path), verbose)?;
+    let png = Png::from_bytes(&data).map_err(hint_extension(Some(&args.input_file_path)))?;
+    trace!(verbose, "parsed {} chunks", png.chunks().len());
 
     let chunk = png.chunk_by_type(&args.chunk_type_str).ok_or(PngError::ChunkNotFound)?;
-    let chunk_data = chunk.data_as_string()?;
-    println!("{}", chunk_data);
+    fs::write(&args.output_file_path, chunk.as_bytes())?;
+    trace!(verbose, "wrote {} ({} bytes) to {}", chunk.chunk_type(), chunk.total_size(), args.output_file_path);
+    Ok(())
+}
+
+pub fn import(args: &ImportArgs, verbose: bool) -> Result<(), Box<dyn Error>> {
+    let data = read_input(Some(&args.input_file_path), verbose)?;
+    let mut png = Png::from_bytes(&data).map_err(hint_extension(Some(&args.input_file_path)))?;
+    trace!(verbose, "parsed {} chunks", png.chunks().len());
+
+    let chunk_data = read_input(Some(&args.chunk_file_path), verbose)?;
+    let chunk = Chunk::try_from(chunk_data.as_ref())?;
+
+    let description = format!("{} ({} bytes)", chunk.chunk_type(), chunk.length());
+    if png.insert_before_iend(chunk) {
+        trace!(verbose, "inserted {} before IEND", description);
+    } else {
+        eprintln!("Warning: no IEND chunk found, appending the chunk at the end");
+    }
+
+    write_png(output_path(Some(&args.input_file_path), args.output_file_path.as_deref()), &png, verbose)?;
+    Ok(())
+}
+
+pub fn checksum(args: &ChecksumArgs, verbose: bool) -> Result<(), Box<dyn Error>> {
+    let data = read_input(Some(&args.input_file_path), verbose)?;
+    let png = Png::from_bytes(&data).map_err(hint_extension(Some(&args.input_file_path)))?;
+    trace!(verbose, "parsed {} chunks", png.chunks().len());
+
+    let bytes = png.as_bytes();
+    let digest = match args.algorithm {
+        ChecksumAlgorithm::Crc32 => format!("{:08x}", Crc::<u32>::new(&CRC_32_ISO_HDLC).checksum(&bytes)),
+        ChecksumAlgorithm::Sha256 => sha256_hex(&bytes)?,
+    };
+    println!("{}", digest);
+    Ok(())
+}
+
+pub fn sanitize(args: &SanitizeArgs, verbose: bool) -> Result<(), Box<dyn Error>> {
+    let data = read_input(Some(&args.input_file_path), verbose)?;
+    let mut png = Png::from_bytes(&data).map_err(hint_extension(Some(&args.input_file_path)))?;
+    trace!(verbose, "parsed {} chunks", png.chunks().len());
+
+    let keep = args.keep.iter()
+        .map(|s| ChunkType::from_str(s))
+        .collect::<Result<Vec<_>, _>>()?;
+    println!("Removed {} ancillary chunks", strip_ancillary(&mut png, &keep));
+    if !png.trailing_bytes().is_empty() {
+        println!("Removed {} bytes after IEND", png.trailing_bytes().len());
+        png.set_trailing_bytes(Vec::new());
+    }
+
+    let path = output_path(Some(&args.input_file_path), args.output_file_path.as_deref());
+    if args.write_if_changed && is_unchanged(Some(&args.input_file_path), path, &png, &data) {
+        trace!(verbose, "{} is unchanged, not writing it", args.input_file_path);
+        return Ok(());
+    }
+    write_png(path, &png, verbose)?;
     Ok(())
 }
 
-pub fn remove(args: &RemoveArgs) -> Result<(), Box<dyn Error>> {
-    let data = fs::read(&args.input_file_path)?;
-    let mut png = Png::try_from(data.as_ref())?;
+pub fn rename_type(args: &RenameArgs, verbose: bool) -> Result<(), Box<dyn Error>> {
+    let data = read_input(Some(&args.input_file_path), verbose)?;
+    let mut png = Png::from_bytes(&data).map_err(hint_extension(Some(&args.input_file_path)))?;
+    trace!(verbose, "parsed {} chunks", png.chunks().len());
+
+    let old_type = ChunkType::from_str(&args.old_type)?;
+    let new_type = ChunkType::from_str(&args.new_type)?;
+    if old_type.is_critical() || new_type.is_critical() {
+        return Err("Critical chunks cannot be renamed".into());
+    }
 
-    png.remove_chunk(&args.chunk_type_str)?;
-    fs::write(&args.input_file_path, png.as_bytes())?;
+    let chunk = png.iter_mut().find(|c| *c.chunk_type() == old_type).ok_or(PngError::ChunkNotFound)?;
+    chunk.set_type(new_type);
+    trace!(verbose, "renamed {} to {}", old_type, chunk.chunk_type());
+
+    write_png(output_path(Some(&args.input_file_path), args.output_file_path.as_deref()), &png, verbose)?;
     Ok(())
 }
 
-pub fn print(args: &PrintArgs) -> Result<(), Box<dyn Error>> {
-    let data = fs::read(&args.input_file_path)?;
-    let png = Png::try_from(data.as_ref())?;
+pub fn set_text(args: &SetTextArgs, verbose: bool) -> Result<(), Box<dyn Error>> {
+    let data = read_input(Some(&args.input_file_path), verbose)?;
+    let mut png = Png::from_bytes(&data).map_err(hint_extension(Some(&args.input_file_path)))?;
+    trace!(verbose, "parsed {} chunks", png.chunks().len());
+
+    png.insert_text_chunk(&args.keyword, &args.text)?;
+    trace!(verbose, "inserted tEXt for {}", args.keyword);
 
-    println!("{}", png);
+    write_png(output_path(Some(&args.input_file_path), args.output_file_path.as_deref()), &png, verbose)?;
     Ok(())
-}
\ No newline at end of file
+}
+
+pub fn canonicalize(args: &CanonicalizeArgs, verbose: bool) -> Result<(), Box<dyn Error>> {
+    let data = read_input(Some(&args.input_file_path), verbose)?;
+    let png = Png::try_from_lenient(&data).map_err(hint_extension(Some(&args.input_file_path)))?;
+    trace!(verbose, "parsed {} chunks", png.chunks().len());
+
+    let png = canonical(png);
+    write_png(output_path(Some(&args.input_file_path), args.output_file_path.as_deref()), &png, verbose)?;
+    Ok(())
+}
+
+pub fn dedup(args: &DedupArgs, verbose: bool) -> Result<(), Box<dyn Error>> {
+    let data = read_input(Some(&args.input_file_path), verbose)?;
+    let mut png = Png::from_bytes(&data).map_err(hint_extension(Some(&args.input_file_path)))?;
+    trace!(verbose, "parsed {} chunks", png.chunks().len());
+
+    let keep = match args.dedupe_keep {
+        DedupeKeep::First => DuplicateKeep::First,
+        DedupeKeep::Last => DuplicateKeep::Last,
+    };
+    println!("Removed {} duplicate chunks", png.deduplicate_chunks(keep));
+
+    write_png(output_path(Some(&args.input_file_path), args.output_file_path.as_deref()), &png, verbose)?;
+    Ok(())
+}
+
+pub fn strip_trailing(args: &StripTrailingArgs, verbose: bool) -> Result<(), Box<dyn Error>> {
+    let data = read_input(Some(&args.input_file_path), verbose)?;
+    let mut png = Png::from_bytes(&data).map_err(hint_extension(Some(&args.input_file_path)))?;
+    trace!(verbose, "parsed {} chunks", png.chunks().len());
+
+    println!("Removed {} bytes after IEND", png.trailing_bytes().len());
+    png.set_trailing_bytes(Vec::new());
+
+    write_png(output_path(Some(&args.input_file_path), args.output_file_path.as_deref()), &png, verbose)?;
+    Ok(())
+}
+
+/// Runs until interrupted, e.g. with Ctrl-C.
+pub fn watch(args: &WatchArgs, verbose: bool) -> Result<(), Box<dyn Error>> {
+    let interval = Duration::from_millis(args.interval);
+    let mut last_modified = None;
+    loop {
+        // The file may briefly be missing while a tool replaces it
+        let modified = fs::metadata(&args.input_file_path).and_then(|m| m.modified()).ok();
+        if modified.is_some() && modified != last_modified {
+            last_modified = modified;
+            trace!(verbose, "{} changed", args.input_file_path);
+            // Clear the screen and move the cursor to the top left
+            print!("\x1b[2J\x1b[H");
+            match read_input(Some(&args.input_file_path), verbose) {
+                Ok(data) => match Png::from_bytes(&data) {
+                    Ok(png) => println!("{}", png.formatter().verbose(false)),
+                    Err(e) => println!("Error: {}", e),
+                },
+                Err(e) => println!("Error: {}", e),
+            }
+            io::stdout().flush()?;
+        }
+        thread::sleep(interval);
+    }
+}
+
+pub fn batch_encode(args: &BatchEncodeArgs, verbose: bool) -> Result<(), Box<dyn Error>> {
+    let manifest = fs::read_to_string(&args.manifest_path)?;
+    let entries = parse_manifest(&manifest)?;
+    let chunk_type = ChunkType::from_str(&args.chunk_type_str)?;
+
+    let mut failed = 0;
+    for (path, message) in &entries {
+        match encode_in_place(path, &chunk_type, message, verbose) {
+            Ok(()) => println!("{}: done", path),
+            Err(e) => {
+                println!("{}: failed: {}", path, e);
+                failed += 1;
+            }
+        }
+    }
+    if failed > 0 {
+        return Err(format!("{} of {} files failed", failed, entries.len()).into());
+    }
+    Ok(())
+}
+
+/// Splits a manifest into `(path, message)` pairs, one per non-empty
+/// line, with a tab between the path and the message.
+fn parse_manifest(manifest: &str) -> Result<Vec<(&str, &str)>, String> {
+    manifest.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(idx, line)| match line.split_once('\t') {
+            Some((path, message)) if !path.is_empty() => Ok((path, message)),
+            _ => Err(format!("Line {}: expected a path and a message separated by a tab", idx + 1)),
+        })
+        .collect()
+}
+
+fn encode_in_place(path: &str, chunk_type: &ChunkType, message: &str, verbose: bool) -> Result<(), Box<dyn Error>> {
+    let data = read_input(Some(path), verbose)?;
+    let mut png = Png::from_bytes(&data).map_err(hint_extension(Some(path)))?;
+    if !png.insert_before_iend(Chunk::new(chunk_type.clone(), message.as_bytes().to_vec())) {
+        eprintln!("Warning: no IEND chunk found in {}, appending the chunk at the end", path);
+    }
+    write_png(Some(path), &png, verbose)?;
+    Ok(())
+}
+
+/// Rebuilds `png` with every CRC recomputed from the chunk contents.
+fn canonical(png: Png) -> Png {
+    let trailing = png.trailing_bytes().to_vec();
+    let chunks = png.into_chunks().into_iter().map(Chunk::with_recomputed_crc).collect();
+    let mut png = Png::from_chunks(chunks);
+    png.set_trailing_bytes(trailing);
+    png
+}
+
+/// Removes every ancillary chunk whose type is not in `keep` and returns
+/// how many chunks were removed.
+fn strip_ancillary(png: &mut Png, keep: &[ChunkType]) -> usize {
+    let num_chunks = png.chunks().len();
+    png.retain_chunks(|chunk| chunk.chunk_type().is_critical() || keep.contains(chunk.chunk_type()));
+    num_chunks - png.chunks().len()
+}
+
+#[cfg(feature = "sha256")]
+fn sha256_hex(bytes: &[u8]) -> Result<String, Box<dyn Error>> {
+    use sha2::{Digest, Sha256};
+
+    Ok(Sha256::digest(bytes).iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+#[cfg(not(feature = "sha256"))]
+fn sha256_hex(_bytes: &[u8]) -> Result<String, Box<dyn Error>> {
+    Err("sha256 is not available, rebuild pngme with `--features sha256`".into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{minimal_png, minimal_png_bytes};
+    use std::path::PathBuf;
+    use std::process;
+
+    /// Creates an empty scratch directory, unique to the test and process.
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("pngme-{}-{}", name, process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn encode_args(input_file_path: &str, output_file_path: Option<&str>) -> EncodeArgs {
+        EncodeArgs {
+            input_file_path: Some(input_file_path.to_string()),
+            chunk_type_str: Some("ruSt".to_string()),
+            message: Some("This is a secret".to_string()),
+            output_file_path: output_file_path.map(|p| p.to_string()),
+            random_type: false,
+            seed: None,
+            strict: false,
+            pipe: false,
+            no_iend_move: false,
+            before_idat: false,
+            encrypt: false,
+            passphrase: None,
+            copy: true,
+            upsert: false,
+            write_if_changed: false,
+            always_write: false,
+        }
+    }
+
+    #[test]
+    fn test_encode_upsert_replaces_existing_chunk() {
+        let dir = test_dir("encode-upsert");
+        let path = dir.join("in.png");
+        fs::write(&path, minimal_png_bytes()).unwrap();
+        let path = path.to_str().unwrap();
+
+        let mut args = encode_args(path, None);
+        args.copy = false;
+        args.upsert = true;
+        encode(&args, false).unwrap();
+        args.message = Some("Second secret".to_string());
+        encode(&args, false).unwrap();
+
+        let png = Png::from_bytes(fs::read(path).unwrap()).unwrap();
+        assert_eq!(png.indices_of("ruSt").len(), 1);
+        assert_eq!(png.chunk_data_by_type("ruSt"), Some(&b"Second secret"[..]));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_write_if_changed_skips_noop_writes() {
+        let dir = test_dir("write-if-changed");
+        let path = dir.join("in.png");
+        fs::write(&path, minimal_png_bytes()).unwrap();
+        let old = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000_000);
+        let modified = |p: &PathBuf| fs::metadata(p).unwrap().modified().unwrap();
+        let path_str = path.to_str().unwrap();
+
+        let mut args = encode_args(path_str, None);
+        args.copy = false;
+        args.upsert = true;
+        args.write_if_changed = true;
+        encode(&args, false).unwrap();
+        File::options().write(true).open(&path).unwrap().set_modified(old).unwrap();
+        encode(&args, false).unwrap();
+        assert_eq!(modified(&path), old);
+
+        let args = RemoveArgs {
+            input_file_path: Some(path_str.to_string()),
+            chunk_type_str: Some("teSt".to_string()),
+            glob: None,
+            pipe: false,
+            if_present: true,
+            normalize_case: false,
+            write_if_changed: true,
+            always_write: false,
+        };
+        remove(&args, false).unwrap();
+        assert_eq!(modified(&path), old);
+
+        let args = RemoveArgs { chunk_type_str: Some("ruSt".to_string()), ..args };
+        remove(&args, false).unwrap();
+        assert_ne!(modified(&path), old);
+        assert_eq!(fs::read(&path).unwrap(), minimal_png_bytes());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_strip_trailing() {
+        let dir = test_dir("strip-trailing");
+        let input = dir.join("in.png");
+        let mut data = minimal_png_bytes();
+        data.extend_from_slice(b"hidden after IEND");
+        fs::write(&input, &data).unwrap();
+        let output = dir.join("out.png");
+
+        let args = StripTrailingArgs {
+            input_file_path: input.to_str().unwrap().to_string(),
+            output_file_path: Some(output.to_str().unwrap().to_string()),
+        };
+        strip_trailing(&args, false).unwrap();
+        assert_eq!(fs::read(&output).unwrap(), minimal_png_bytes());
+        assert_eq!(fs::read(&input).unwrap(), data);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    fn decode_args(input_file_path: &str, chunk_type_str: &str) -> DecodeArgs {
+        DecodeArgs {
+            input_file_path: input_file_path.to_string(),
+            chunk_type_str: Some(chunk_type_str.to_string()),
+            glob: None,
+            all: false,
+            expect_count: None,
+            delimiter: "\\n".to_string(),
+            lossy: false,
+            max_chars: None,
+            exists: false,
+            ignore_crc: false,
+            decrypt: false,
+            passphrase: None,
+            max_chunks: None,
+            input_format: InputFormat::Auto,
+            trailing: false,
+            output: None,
+            progress: false,
+            last: false,
+        }
+    }
+
+    #[test]
+    fn test_decode_returns_messages() {
+        let dir = test_dir("decode");
+        let path = dir.join("in.png");
+        let mut png = minimal_png();
+        png.insert_before_iend(Chunk::try_new("ruSt", b"first".to_vec()).unwrap());
+        png.insert_before_iend(Chunk::try_new("ruSt", b"second".to_vec()).unwrap());
+        png.set_trailing_bytes(b"appended".to_vec());
+        fs::write(&path, png.as_bytes()).unwrap();
+        let path = path.to_str().unwrap();
+
+        let mut args = decode_args(path, "ruSt");
+        assert_eq!(decode(&args, false).unwrap(), b"first\n");
+        args.last = true;
+        assert_eq!(decode(&args, false).unwrap(), b"second\n");
+        args.last = false;
+        args.all = true;
+        args.delimiter = ",".to_string();
+        assert_eq!(decode(&args, false).unwrap(), b"first,second,");
+        args.max_chars = Some(3);
+        assert_eq!(decode(&args, false).unwrap(), "fir…,sec…,".as_bytes());
+
+        let mut args = decode_args(path, "teSt");
+        assert!(decode(&args, false).is_err());
+        args.exists = true;
+        let err = decode(&args, false).unwrap_err();
+        assert!(matches!(err.downcast_ref::<PngError>(), Some(PngError::ChunkNotFound)));
+        args.chunk_type_str = Some("ruSt".to_string());
+        assert_eq!(decode(&args, false).unwrap(), b"");
+        args.exists = false;
+        args.chunk_type_str = None;
+        args.trailing = true;
+        assert_eq!(decode(&args, false).unwrap(), b"appended");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_remove_normalize_case() {
+        let dir = test_dir("remove-normalize-case");
+        let path = dir.join("in.png");
+        let mut png = minimal_png();
+        png.insert_before_iend(Chunk::try_new("RuSt", b"first".to_vec()).unwrap());
+        png.insert_before_iend(Chunk::try_new("ruSt", b"second".to_vec()).unwrap());
+        png.insert_before_iend(Chunk::try_new("ruSx", b"third".to_vec()).unwrap());
+        fs::write(&path, png.as_bytes()).unwrap();
+
+        let mut args = RemoveArgs {
+            input_file_path: Some(path.to_str().unwrap().to_string()),
+            chunk_type_str: Some("ruSt".to_string()),
+            glob: None,
+            pipe: false,
+            if_present: false,
+            normalize_case: true,
+            write_if_changed: false,
+            always_write: false,
+        };
+        remove(&args, false).unwrap();
+        let png = Png::from_bytes(fs::read(&path).unwrap()).unwrap();
+        assert_eq!(png.chunks().len(), 4);
+        assert_eq!(png.chunk_data_by_type("ruSx"), Some(&b"third"[..]));
+
+        assert!(remove(&args, false).is_err());
+        args.if_present = true;
+        assert!(remove(&args, false).is_ok());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_output_path_same_file() {
+        let dir = test_dir("output-path");
+        let input = dir.join("in.png");
+        fs::write(&input, b"").unwrap();
+        let input = input.to_str().unwrap();
+        let same = dir.join(".").join("in.png");
+        let same = same.to_str().unwrap();
+        let other = dir.join("out.png");
+        let other = other.to_str().unwrap();
+
+        assert_eq!(output_path(Some(input), Some(same)), Some(input));
+        assert_eq!(output_path(Some(same), Some(input)), Some(same));
+        assert_eq!(output_path(Some(input), Some(other)), Some(other));
+        assert_eq!(output_path(Some(input), None), Some(input));
+        assert_eq!(output_path(None, None), None);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_encode_copy_leaves_source_untouched() {
+        let dir = test_dir("encode-copy");
+        let source = minimal_png_bytes();
+        let input = dir.join("in.png");
+        fs::write(&input, &source).unwrap();
+        let input = input.to_str().unwrap();
+
+        encode(&encode_args(input, None), false).unwrap();
+        assert_eq!(fs::read(input).unwrap(), source);
+        let copy = fs::read(dir.join("in.embedded.png")).unwrap();
+        let png = Png::from_bytes(copy).unwrap();
+        assert_eq!(png.chunk_data_by_type("ruSt"), Some(&b"This is a secret"[..]));
+
+        let output = dir.join("out.png");
+        encode(&encode_args(input, output.to_str()), false).unwrap();
+        assert_eq!(fs::read(input).unwrap(), source);
+        assert!(output.exists());
+
+        assert!(encode(&encode_args(input, Some(input)), false).is_err());
+        assert_eq!(fs::read(input).unwrap(), source);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_strip_ancillary_keeps_listed_types() {
+        let mut png = minimal_png();
+        png.insert_before_iend(Chunk::try_new("sRGB", vec![0]).unwrap());
+        png.insert_before_iend(Chunk::try_new("tEXt", b"Comment".to_vec()).unwrap());
+        png.insert_before_iend(Chunk::try_new("ruSt", b"This is a secret".to_vec()).unwrap());
+
+        assert_eq!(strip_ancillary(&mut png, &[ChunkType::from_str("sRGB").unwrap()]), 2);
+        let chunk_types: Vec<String> = png.chunks().iter().map(|c| c.chunk_type().to_string()).collect();
+        assert_eq!(chunk_types, ["IHDR", "IDAT", "sRGB", "IEND"]);
+
+        assert_eq!(strip_ancillary(&mut png, &[]), 1);
+        assert_eq!(png.chunks().len(), 3);
+    }
+
+    #[test]
+    fn test_canonical_is_stable() {
+        let clean = minimal_png_bytes();
+        let mut damaged = clean.clone();
+        let last = damaged.len() - 1;
+        damaged[last] ^= 0xff;
+
+        let clean = canonical(Png::try_from_lenient(&clean).unwrap()).as_bytes();
+        let damaged = canonical(Png::try_from_lenient(&damaged).unwrap()).as_bytes();
+        assert_eq!(clean, damaged);
+        assert_eq!(clean, minimal_png_bytes());
+    }
+
+    #[test]
+    fn test_hint_extension() {
+        let hint = |path, err| hint_extension(path)(err).to_string();
+        assert_eq!(
+            hint(Some("photo.jpg"), PngError::BadHeader),
+            "Header length or pattern mismatch\nnote: file extension is .jpg; is this actually a PNG?"
+        );
+        assert!(hint(Some("photo.JPEG"), PngError::BadSignature(3)).ends_with("is .JPEG; is this actually a PNG?"));
+        assert!(!hint(Some("image.png"), PngError::BadHeader).contains("note"));
+        assert!(!hint(Some("image.PNG"), PngError::BadHeader).contains("note"));
+        assert!(!hint(Some("image"), PngError::BadHeader).contains("note"));
+        assert!(!hint(None, PngError::BadHeader).contains("note"));
+        assert!(!hint(Some("photo.jpg"), PngError::BadLen).contains("note"));
+    }
+
+    #[test]
+    fn test_truncate_chars() {
+        assert_eq!(truncate_chars("hello", 5), "hello");
+        assert_eq!(truncate_chars("hello", 3), "hel…");
+        assert_eq!(truncate_chars("", 0), "");
+        // "é" and "ö" are two bytes each
+        assert_eq!(truncate_chars("héllö wörld", 5), "héllö…");
+        assert_eq!(truncate_chars("héllö wörld", 4), "héll…");
+        assert_eq!(truncate_chars("héllö", 1), "h…");
+    }
+
+    #[test]
+    fn test_parse_manifest() {
+        let manifest = "a.png\tfirst message\n\nsub/b.png\tsecond\twith tab\n";
+        assert_eq!(
+            parse_manifest(manifest).unwrap(),
+            [("a.png", "first message"), ("sub/b.png", "second\twith tab")]
+        );
+
+        let manifest = "a.png\tfirst\nb.png second\n";
+        assert_eq!(
+            parse_manifest(manifest).unwrap_err(),
+            "Line 2: expected a path and a message separated by a tab"
+        );
+        assert!(parse_manifest("\tno path").is_err());
+    }
+
+    #[test]
+    fn test_batch_encode_reports_failures() {
+        let dir = test_dir("batch-encode");
+        let good = dir.join("good.png");
+        fs::write(&good, minimal_png_bytes()).unwrap();
+        let manifest = dir.join("manifest.tsv");
+        let missing = dir.join("missing.png");
+        fs::write(&manifest, format!("{}\tfirst\n{}\tsecond\n", good.display(), missing.display())).unwrap();
+
+        let args = BatchEncodeArgs {
+            manifest_path: manifest.to_str().unwrap().to_string(),
+            chunk_type_str: "ruSt".to_string(),
+        };
+        assert_eq!(batch_encode(&args, false).unwrap_err().to_string(), "1 of 2 files failed");
+        let png = Png::from_bytes(fs::read(&good).unwrap()).unwrap();
+        assert_eq!(png.chunk_data_by_type("ruSt"), Some(&b"first"[..]));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_percent() {
+        assert_eq!(percent(0, 200), 0);
+        assert_eq!(percent(99, 200), 49);
+        assert_eq!(percent(200, 200), 100);
+        assert_eq!(percent(300, 200), 100);
+        assert_eq!(percent(0, 0), 100);
+    }
+
+    #[test]
+    fn test_check_count() {
+        assert!(check_count(2, 2).is_ok());
+        assert_eq!(check_count(2, 3).unwrap_err().to_string(), "Expected 2 matching chunks, found 3");
+        assert_eq!(check_count(1, 0).unwrap_err().to_string(), "Expected 1 matching chunks, found 0");
+    }
+
+    #[test]
+    fn test_idat_summary() {
+        let png = minimal_png();
+        assert_eq!(idat_summary(&png).unwrap(), format!("1 chunk, {} bytes total (image data)", png.idat_len()));
+
+        let chunks = (0..16).map(|_| Chunk::try_new("IDAT", vec![0; 128 * 1024]).unwrap()).collect();
+        let png = Png::from_chunks(chunks);
+        assert_eq!(idat_summary(&png).unwrap(), "16 chunks, 2,097,152 bytes total (image data)");
+
+        let png = Png::from_chunks(Vec::new());
+        assert_eq!(idat_summary(&png), None);
+    }
+
+    #[test]
+    fn test_group_thousands() {
+        assert_eq!(group_thousands(0), "0");
+        assert_eq!(group_thousands(999), "999");
+        assert_eq!(group_thousands(1000), "1,000");
+        assert_eq!(group_thousands(2_097_152), "2,097,152");
+    }
+
+    #[test]
+    fn test_json_report() {
+        let good = Chunk::try_new("ruSt", b"hi".to_vec()).unwrap();
+        let bad = Chunk::from_parts(ChunkType::from_str("ruSt").unwrap(), b"hi".to_vec(), good.crc() ^ 1);
+        let chunks = [good.clone(), bad.clone()];
+
+        assert_eq!(
+            json_report(&chunks, true),
+            format!(
+                "{{\"chunks\":[{{\"type\":\"ruSt\",\"length\":2,\"crc\":{},\"crc_valid\":true,\"data\":\"6869\"}},\
+                {{\"type\":\"ruSt\",\"length\":2,\"crc\":{},\"crc_valid\":false,\"data\":\"6869\"}}]}}",
+                good.crc(),
+                bad.crc(),
+            )
+        );
+        assert!(!json_report(&chunks, false).contains("crc_valid"));
+        assert_eq!(json_report(&[], true), "{\"chunks\":[]}");
+    }
+
+    #[test]
+    fn test_json_string() {
+        assert_eq!(json_string("ruSt"), "\"ruSt\"");
+        assert_eq!(json_string("\\x01\"\n"), "\"\\\\x01\\\"\\u000a\"");
+    }
+
+    #[test]
+    fn test_write_raw_records() {
+        let chunks = [
+            Chunk::try_new("ruSt", b"hi".to_vec()).unwrap(),
+            Chunk::try_new("IEND", Vec::new()).unwrap(),
+        ];
+        let mut out = Vec::new();
+        write_raw_records(&chunks, &mut out).unwrap();
+        assert_eq!(out, b"ruSt\0\0\0\x02hiIEND\0\0\0\0");
+    }
+
+    #[test]
+    fn test_invalid_reason() {
+        let reason = |s| invalid_reason(&ChunkType::from_str(s).unwrap());
+        assert_eq!(reason("IHDR"), None);
+        assert_eq!(reason("tEXt"), None);
+        assert_eq!(reason("ruSt"), None);
+        assert_eq!(reason("rust"), Some("reserved bit is set"));
+        assert_eq!(reason("RuSt"), Some("unknown critical chunk"));
+    }
+
+    #[test]
+    fn test_format_size() {
+        assert_eq!(format_size(0), "0 B");
+        assert_eq!(format_size(1023), "1023 B");
+        assert_eq!(format_size(1024), "1.0 KiB");
+        assert_eq!(format_size(1536), "1.5 KiB");
+        assert_eq!(format_size(1048576), "1.0 MiB");
+        assert_eq!(format_size(2202009), "2.1 MiB");
+    }
+}
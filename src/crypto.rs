@@ -0,0 +1,154 @@
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+
+/// Marks chunk data written by `encrypt`. It is followed by the KDF salt,
+/// the nonce and the ChaCha20-Poly1305 ciphertext including its tag.
+pub const MAGIC: [u8; 4] = *b"PMe1";
+
+const SALT_BYTES: usize = 16;
+const NONCE_BYTES: usize = 12;
+const TAG_BYTES: usize = 16;
+const HEADER_BYTES: usize = MAGIC.len() + SALT_BYTES + NONCE_BYTES;
+#[cfg(feature = "encrypt")]
+const KDF_ROUNDS: u32 = 100_000;
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum CryptoError {
+    Unavailable,
+    NotEncrypted,
+    Encrypt,
+    Decrypt,
+    Random,
+}
+
+impl Display for CryptoError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CryptoError::Unavailable => write!(f, "Encryption is not available, rebuild pngme with `--features encrypt`"),
+            CryptoError::NotEncrypted => write!(f, "Chunk data is not encrypted"),
+            CryptoError::Encrypt => write!(f, "Could not encrypt the message"),
+            CryptoError::Decrypt => write!(f, "Wrong passphrase or corrupted data"),
+            CryptoError::Random => write!(f, "Could not gather random bytes"),
+        }
+    }
+}
+
+impl Error for CryptoError {}
+
+/// Tells whether `data` looks like the output of `encrypt`, i.e. starts
+/// with the magic bytes and is long enough to hold the header and tag.
+pub fn is_encrypted(data: &[u8]) -> bool {
+    data.len() >= HEADER_BYTES + TAG_BYTES && data.starts_with(&MAGIC)
+}
+
+/// Stretches the passphrase into a 256 bit key by hashing it together
+/// with the salt and then rehashing the result `KDF_ROUNDS` times.
+#[cfg(feature = "encrypt")]
+fn derive_key(passphrase: &str, salt: &[u8]) -> [u8; 32] {
+    use sha2::{Digest, Sha256};
+
+    let mut key = [0u8; 32];
+    key.copy_from_slice(&Sha256::new().chain_update(salt).chain_update(passphrase.as_bytes()).finalize());
+    for _ in 1..KDF_ROUNDS {
+        let digest = Sha256::new().chain_update(key).chain_update(salt).finalize();
+        key.copy_from_slice(&digest);
+    }
+    key
+}
+
+/// Encrypts `plaintext` with a key derived from `passphrase`. A fresh
+/// salt and nonce are generated for every call and stored in the header.
+#[cfg(feature = "encrypt")]
+pub fn encrypt(plaintext: &[u8], passphrase: &str) -> Result<Vec<u8>, CryptoError> {
+    use chacha20poly1305::aead::{Aead, KeyInit};
+    use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+
+    let mut salt = [0u8; SALT_BYTES];
+    let mut nonce = [0u8; NONCE_BYTES];
+    getrandom::getrandom(&mut salt).map_err(|_| CryptoError::Random)?;
+    getrandom::getrandom(&mut nonce).map_err(|_| CryptoError::Random)?;
+
+    let key = derive_key(passphrase, &salt);
+    let ciphertext = ChaCha20Poly1305::new(Key::from_slice(&key))
+        .encrypt(Nonce::from_slice(&nonce), plaintext)
+        .map_err(|_| CryptoError::Encrypt)?;
+
+    Ok(MAGIC.iter()
+        .chain(salt.iter())
+        .chain(nonce.iter())
+        .chain(ciphertext.iter())
+        .copied()
+        .collect())
+}
+
+/// Reverses `encrypt`. Fails with `NotEncrypted` if `data` has no pngme
+/// header and with `Decrypt` if the passphrase is wrong or the data was
+/// tampered with.
+#[cfg(feature = "encrypt")]
+pub fn decrypt(data: &[u8], passphrase: &str) -> Result<Vec<u8>, CryptoError> {
+    use chacha20poly1305::aead::{Aead, KeyInit};
+    use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+
+    if !is_encrypted(data) {
+        return Err(CryptoError::NotEncrypted);
+    }
+    let (salt, rest) = data[MAGIC.len()..].split_at(SALT_BYTES);
+    let (nonce, ciphertext) = rest.split_at(NONCE_BYTES);
+
+    let key = derive_key(passphrase, salt);
+    ChaCha20Poly1305::new(Key::from_slice(&key))
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map_err(|_| CryptoError::Decrypt)
+}
+
+#[cfg(not(feature = "encrypt"))]
+pub fn encrypt(_plaintext: &[u8], _passphrase: &str) -> Result<Vec<u8>, CryptoError> {
+    Err(CryptoError::Unavailable)
+}
+
+#[cfg(not(feature = "encrypt"))]
+pub fn decrypt(_data: &[u8], _passphrase: &str) -> Result<Vec<u8>, CryptoError> {
+    Err(CryptoError::Unavailable)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_encrypted() {
+        let mut data = MAGIC.to_vec();
+        data.extend_from_slice(&[0; SALT_BYTES + NONCE_BYTES + TAG_BYTES]);
+        assert!(is_encrypted(&data));
+        assert!(!is_encrypted(&data[..data.len() - 1]));
+        assert!(!is_encrypted(b"This is where your secret message will be!"));
+    }
+
+    #[cfg(feature = "encrypt")]
+    #[test]
+    fn test_encrypt_round_trip() {
+        let encrypted = encrypt(b"This is a secret", "hunter2").unwrap();
+        assert!(is_encrypted(&encrypted));
+        assert_eq!(decrypt(&encrypted, "hunter2").unwrap(), b"This is a secret");
+    }
+
+    #[cfg(feature = "encrypt")]
+    #[test]
+    fn test_decrypt_wrong_passphrase() {
+        let encrypted = encrypt(b"This is a secret", "hunter2").unwrap();
+        assert_eq!(decrypt(&encrypted, "hunter3"), Err(CryptoError::Decrypt));
+    }
+
+    #[cfg(feature = "encrypt")]
+    #[test]
+    fn test_decrypt_not_encrypted() {
+        assert_eq!(decrypt(b"This is a secret", "hunter2"), Err(CryptoError::NotEncrypted));
+    }
+
+    #[cfg(not(feature = "encrypt"))]
+    #[test]
+    fn test_encrypt_unavailable() {
+        assert_eq!(encrypt(b"This is a secret", "hunter2"), Err(CryptoError::Unavailable));
+        assert_eq!(decrypt(b"This is a secret", "hunter2"), Err(CryptoError::Unavailable));
+    }
+}
@@ -0,0 +1,88 @@
+//! Example invocations shown after the `--help` text of each subcommand.
+
+pub const ENCODE: &str = "\
+EXAMPLES:
+    pngme encode in.png ruSt \"hi\" out.png
+    pngme encode in.png ruSt \"hi\" --copy
+    pngme encode --random-type in.png \"hi\"
+    cat in.png | pngme encode --pipe ruSt \"hi\" > out.png";
+
+pub const DECODE: &str = "\
+EXAMPLES:
+    pngme decode in.png ruSt
+    pngme decode in.png ruSt --all --delimiter ', '
+    pngme decode in.png --glob 'ru*'
+    pngme decode in.png --trailing --output appended.bin";
+
+pub const REMOVE: &str = "\
+EXAMPLES:
+    pngme remove in.png ruSt
+    pngme remove in.png --glob 'ru*'
+    pngme remove in.png ruSt --normalize-case
+    cat in.png | pngme remove --pipe ruSt > out.png";
+
+pub const PRINT: &str = "\
+EXAMPLES:
+    pngme print in.png
+    pngme print in.png --brief --limit 10
+    pngme print in.png --count --sort
+    pngme print in.png --show-invalid
+    pngme print in.png --brief --index";
+
+pub const EXPORT: &str = "\
+EXAMPLES:
+    pngme export in.png ruSt ruSt.chunk";
+
+pub const IMPORT: &str = "\
+EXAMPLES:
+    pngme import other.png ruSt.chunk
+    pngme import other.png ruSt.chunk out.png";
+
+pub const CHECKSUM: &str = "\
+EXAMPLES:
+    pngme checksum in.png
+    pngme checksum in.png --algorithm sha256";
+
+pub const SANITIZE: &str = "\
+EXAMPLES:
+    pngme sanitize in.png clean.png
+    pngme sanitize in.png --keep sRGB --keep gAMA";
+
+pub const RENAME_TYPE: &str = "\
+EXAMPLES:
+    pngme rename-type in.png ruSt ruSx
+    pngme rename-type in.png ruSt ruSx out.png";
+
+pub const SET_TEXT: &str = "\
+EXAMPLES:
+    pngme set-text in.png Author 'Jane Doe'
+    pngme set-text in.png Comment 'Taken in 2021' out.png";
+
+pub const CANONICALIZE: &str = "\
+EXAMPLES:
+    pngme canonicalize in.png out.png";
+
+pub const DEDUP: &str = "\
+EXAMPLES:
+    pngme dedup in.png out.png
+    pngme dedup in.png --dedupe-keep last";
+
+pub const STRIP_TRAILING: &str = "\
+EXAMPLES:
+    pngme strip-trailing in.png
+    pngme strip-trailing in.png clean.png";
+
+pub const WATCH: &str = "\
+EXAMPLES:
+    pngme watch in.png
+    pngme watch in.png --interval 100";
+
+pub const BATCH_ENCODE: &str = "\
+EXAMPLES:
+    printf 'a.png\\tfirst\\nb.png\\tsecond\\n' > manifest.tsv
+    pngme batch-encode manifest.tsv ruSt";
+
+pub const COMPLETIONS: &str = "\
+EXAMPLES:
+    pngme completions zsh > _pngme
+    pngme completions bash > /etc/bash_completion.d/pngme";
@@ -0,0 +1,216 @@
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+
+use crate::chunk::Chunk;
+use crate::chunk_type::ChunkType;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum ColorType {
+    Grayscale,
+    Rgb,
+    Palette,
+    GrayscaleAlpha,
+    Rgba,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Ihdr {
+    width: u32,
+    height: u32,
+    bit_depth: u8,
+    color_type: ColorType,
+    interlaced: bool,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum IhdrError {
+    NotIhdr,
+    BadLen,
+    BadColorType(u8),
+    BadBitDepth(ColorType, u8),
+    BadInterlaceMethod(u8),
+}
+
+impl Display for IhdrError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            IhdrError::NotIhdr => write!(f, "Chunk is not an IHDR chunk"),
+            IhdrError::BadLen => write!(f, "IHDR data must be exactly {} bytes", Ihdr::DATA_BYTES),
+            IhdrError::BadColorType(c) => write!(f, "Unknown color type {}", c),
+            IhdrError::BadBitDepth(c, d) => write!(f, "Bit depth {} is not allowed for color type {}", d, c),
+            IhdrError::BadInterlaceMethod(m) => write!(f, "Unknown interlace method {}", m),
+        }
+    }
+}
+
+impl Error for IhdrError {}
+
+impl ColorType {
+    pub fn allowed_bit_depths(&self) -> &'static [u8] {
+        match self {
+            ColorType::Grayscale => &[1, 2, 4, 8, 16],
+            ColorType::Rgb => &[8, 16],
+            ColorType::Palette => &[1, 2, 4, 8],
+            ColorType::GrayscaleAlpha => &[8, 16],
+            ColorType::Rgba => &[8, 16],
+        }
+    }
+}
+
+impl TryFrom<u8> for ColorType {
+    type Error = IhdrError;
+    fn try_from(v: u8) -> Result<Self, Self::Error> {
+        match v {
+            0 => Ok(ColorType::Grayscale),
+            2 => Ok(ColorType::Rgb),
+            3 => Ok(ColorType::Palette),
+            4 => Ok(ColorType::GrayscaleAlpha),
+            6 => Ok(ColorType::Rgba),
+            _ => Err(IhdrError::BadColorType(v)),
+        }
+    }
+}
+
+impl Display for ColorType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ColorType::Grayscale => write!(f, "Grayscale"),
+            ColorType::Rgb => write!(f, "RGB"),
+            ColorType::Palette => write!(f, "Palette"),
+            ColorType::GrayscaleAlpha => write!(f, "Grayscale+Alpha"),
+            ColorType::Rgba => write!(f, "RGBA"),
+        }
+    }
+}
+
+impl Ihdr {
+    pub const DATA_BYTES: usize = 13;
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn bit_depth(&self) -> u8 {
+        self.bit_depth
+    }
+
+    pub fn color_type(&self) -> ColorType {
+        self.color_type
+    }
+
+    pub fn interlaced(&self) -> bool {
+        self.interlaced
+    }
+}
+
+impl TryFrom<&Chunk> for Ihdr {
+    type Error = IhdrError;
+    fn try_from(chunk: &Chunk) -> Result<Self, Self::Error> {
+        if *chunk.chunk_type() != ChunkType::IHDR {
+            return Err(IhdrError::NotIhdr);
+        }
+        let v = chunk.data();
+        if v.len() != Self::DATA_BYTES {
+            return Err(IhdrError::BadLen);
+        }
+
+        let bit_depth = v[8];
+        let color_type = ColorType::try_from(v[9])?;
+        if !color_type.allowed_bit_depths().contains(&bit_depth) {
+            return Err(IhdrError::BadBitDepth(color_type, bit_depth));
+        }
+        let interlaced = match v[12] {
+            0 => false,
+            1 => true,
+            m => return Err(IhdrError::BadInterlaceMethod(m)),
+        };
+
+        Ok(Self {
+            width: u32::from_be_bytes(v[0..4].try_into().unwrap()),
+            height: u32::from_be_bytes(v[4..8].try_into().unwrap()),
+            bit_depth,
+            color_type,
+            interlaced,
+        })
+    }
+}
+
+impl Display for Ihdr {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}x{}, {}, {}-bit, {}",
+            self.width,
+            self.height,
+            self.color_type,
+            self.bit_depth,
+            if self.interlaced { "interlaced" } else { "non-interlaced" },
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn ihdr_chunk(bit_depth: u8, color_type: u8, interlace: u8) -> Chunk {
+        #[rustfmt::skip]
+        let data = vec![
+            0, 0, 0, 50,    // width
+            0, 0, 1, 2,     // height
+            bit_depth,
+            color_type,
+            0,              // compression method
+            0,              // filter method
+            interlace,
+        ];
+        Chunk::new(ChunkType::IHDR, data)
+    }
+
+    #[test]
+    fn test_ihdr_from_chunk() {
+        let ihdr = Ihdr::try_from(&ihdr_chunk(8, 6, 0)).unwrap();
+        assert_eq!(ihdr.width(), 50);
+        assert_eq!(ihdr.height(), 258);
+        assert_eq!(ihdr.bit_depth(), 8);
+        assert_eq!(ihdr.color_type(), ColorType::Rgba);
+        assert!(!ihdr.interlaced());
+    }
+
+    #[test]
+    fn test_ihdr_string() {
+        let ihdr = Ihdr::try_from(&ihdr_chunk(8, 2, 0)).unwrap();
+        assert_eq!(&ihdr.to_string(), "50x258, RGB, 8-bit, non-interlaced");
+
+        let ihdr = Ihdr::try_from(&ihdr_chunk(1, 0, 1)).unwrap();
+        assert_eq!(&ihdr.to_string(), "50x258, Grayscale, 1-bit, interlaced");
+    }
+
+    #[test]
+    fn test_ihdr_bad_color_type() {
+        assert_eq!(Ihdr::try_from(&ihdr_chunk(8, 5, 0)), Err(IhdrError::BadColorType(5)));
+    }
+
+    #[test]
+    fn test_ihdr_bad_bit_depth() {
+        assert_eq!(Ihdr::try_from(&ihdr_chunk(4, 2, 0)), Err(IhdrError::BadBitDepth(ColorType::Rgb, 4)));
+        assert_eq!(Ihdr::try_from(&ihdr_chunk(16, 3, 0)), Err(IhdrError::BadBitDepth(ColorType::Palette, 16)));
+    }
+
+    #[test]
+    fn test_ihdr_bad_interlace_method() {
+        assert_eq!(Ihdr::try_from(&ihdr_chunk(8, 6, 2)), Err(IhdrError::BadInterlaceMethod(2)));
+    }
+
+    #[test]
+    fn test_ihdr_from_other_chunk() {
+        let chunk = Chunk::new(ChunkType::from_str("RuSt").unwrap(), vec![0; Ihdr::DATA_BYTES]);
+        assert_eq!(Ihdr::try_from(&chunk), Err(IhdrError::NotIhdr));
+
+        let chunk = Chunk::new(ChunkType::IHDR, vec![0; 12]);
+        assert_eq!(Ihdr::try_from(&chunk), Err(IhdrError::BadLen));
+    }
+}
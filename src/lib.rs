@@ -0,0 +1,14 @@
+pub mod ancillary;
+pub mod args;
+pub mod chunk;
+pub mod chunk_type;
+pub mod commands;
+pub mod crypto;
+pub mod examples;
+pub mod ihdr;
+pub mod png;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
+
+pub type Error = Box<dyn std::error::Error>;
+pub type Result<T> = std::result::Result<T, Error>;
@@ -1,22 +1,44 @@
-pub mod args;
-pub mod chunk;
-pub mod chunk_type;
-pub mod commands;
-pub mod png;
+use std::io::{self, Write};
+use std::process;
 
-pub type Error = Box<dyn std::error::Error>;
-pub type Result<T> = std::result::Result<T, Error>;
-
-use clap::Parser;
-use args::{Cli, Commands};
+use clap::{CommandFactory, Parser};
+use pngme::args::{Cli, Commands};
+use pngme::png::PngError;
+use pngme::{commands, Result};
 
 fn main() -> Result<()> {
     let cli = Cli::parse();
 
     match &cli.command {
-        Commands::Encode(enc) => commands::encode(enc),
-        Commands::Decode(dec) => commands::decode(dec),
-        Commands::Remove(rem) => commands::remove(rem),
-        Commands::Print(prn) => commands::print(prn),
+        Commands::Encode(enc) => commands::encode(enc, cli.verbose),
+        Commands::Decode(dec) => match commands::decode(dec, cli.verbose) {
+            Ok(output) => {
+                io::stdout().write_all(&output)?;
+                Ok(())
+            }
+            Err(e) if dec.exists && matches!(e.downcast_ref::<PngError>(), Some(PngError::ChunkNotFound)) => {
+                process::exit(1)
+            }
+            Err(e) => Err(e),
+        },
+        Commands::Remove(rem) => commands::remove(rem, cli.verbose),
+        Commands::Print(prn) => commands::print(prn, cli.verbose),
+        Commands::Export(exp) => commands::export(exp, cli.verbose),
+        Commands::Import(imp) => commands::import(imp, cli.verbose),
+        Commands::Checksum(chk) => commands::checksum(chk, cli.verbose),
+        Commands::Sanitize(san) => commands::sanitize(san, cli.verbose),
+        Commands::RenameType(ren) => commands::rename_type(ren, cli.verbose),
+        Commands::SetText(txt) => commands::set_text(txt, cli.verbose),
+        Commands::Canonicalize(canon) => commands::canonicalize(canon, cli.verbose),
+        Commands::Dedup(dedup) => commands::dedup(dedup, cli.verbose),
+        Commands::StripTrailing(strip) => commands::strip_trailing(strip, cli.verbose),
+        Commands::Watch(watch) => commands::watch(watch, cli.verbose),
+        Commands::BatchEncode(batch) => commands::batch_encode(batch, cli.verbose),
+        Commands::Completions(cmp) => {
+            let mut cmd = Cli::command();
+            let name = cmd.get_name().to_string();
+            clap_complete::generate(cmp.shell, &mut cmd, name, &mut std::io::stdout());
+            Ok(())
+        }
     }
 }
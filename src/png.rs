@@ -1,5 +1,7 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::error::Error;
 use std::fmt::{Formatter, Display};
+use std::io::{self, Read, Write};
 use std::str::FromStr;
 
 use crate::chunk::{Chunk,ChunkError};
@@ -7,15 +9,32 @@ use crate::chunk_type::ChunkType;
 
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub struct Png {
-    chunks: Vec<Chunk>
+    chunks: Vec<Chunk>,
+    trailing: Vec<u8>,
+}
+
+/// Which chunk of a group of duplicates `Png::deduplicate_chunks` keeps.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum DuplicateKeep {
+    First,
+    Last,
 }
 
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub enum PngError {
     BadLen,
     BadHeader,
+    BadSignature(usize),
     Chunk(ChunkError),
     ChunkNotFound,
+    TooManyChunks(usize),
+    Io(io::ErrorKind),
+    BadKeyword,
+    NotLatin1(char),
+    IhdrNotFirst,
+    PlteAfterIdat,
+    IdatNotContiguous,
+    IendNotLast,
 }
 
 impl Display for PngError {
@@ -24,11 +43,20 @@ impl Display for PngError {
         match self {
             BadLen => write!(f, "Length mismatch in chunks or header"),
             BadHeader => write!(f, "Header length or pattern mismatch"),
+            BadSignature(offset) => write!(f, "Signature found at offset {0}; file has a {0}-byte prefix", offset),
             Chunk(e) => {
                 write!(f, "Bad Chunk: ")?;
                 e.fmt(f)
             }
             ChunkNotFound => write!(f, "Could not find requested chunk"),
+            TooManyChunks(max) => write!(f, "File has more than {} chunks", max),
+            Io(kind) => write!(f, "Could not read PNG: {}", io::Error::from(*kind)),
+            BadKeyword => write!(f, "Keyword must be 1-79 bytes long and must not contain NUL"),
+            NotLatin1(c) => write!(f, "Text chunks must be Latin-1; \"{}\" is not allowed", c),
+            IhdrNotFirst => write!(f, "IHDR is not the first chunk"),
+            PlteAfterIdat => write!(f, "PLTE appears after IDAT"),
+            IdatNotContiguous => write!(f, "IDAT chunks are not contiguous"),
+            IendNotLast => write!(f, "IEND is not the last chunk"),
         }
     }
 }
@@ -37,10 +65,17 @@ impl Error for PngError {}
 
 impl Png {
     pub const STANDARD_HEADER: [u8; 8] = [137, 80, 78, 71, 13, 10, 26, 10];
+    /// Number of chunks after which parsing gives up, so that a hostile
+    /// file made of millions of tiny chunks cannot exhaust memory.
+    pub const DEFAULT_MAX_CHUNKS: usize = 1_000_000;
+    /// How far into the file a misplaced signature is looked for, e.g.
+    /// after a UTF-8 BOM or stray bytes prepended by some tool.
+    pub const SIGNATURE_SEARCH_BYTES: usize = 64;
 
     pub fn from_chunks(chunks: Vec<Chunk>) -> Png {
         Self {
             chunks,
+            trailing: Vec::new(),
         }
     }
 
@@ -48,12 +83,125 @@ impl Png {
         self.chunks.push(chunk);
     }
 
+    /// Inserts the chunk right before the first chunk of the given type,
+    /// e.g. before the first IDAT for chunks the spec requires to come
+    /// before the image data.
+    pub fn insert_before_type(&mut self, chunk_type: &str, chunk: Chunk) -> Result<(), PngError> {
+        let idx = self.index_of_first(chunk_type).ok_or(PngError::ChunkNotFound)?;
+        self.chunks.insert(idx, chunk);
+        Ok(())
+    }
+
+    /// Adds a `tEXt` chunk holding `keyword`, a NUL separator and `text`
+    /// before the IEND chunk. Both are stored as Latin-1, and the keyword
+    /// must be 1 to 79 bytes long.
+    pub fn insert_text_chunk(&mut self, keyword: &str, text: &str) -> Result<(), PngError> {
+        let mut data = latin1_bytes(keyword)?;
+        if data.is_empty() || data.len() > 79 || data.contains(&0) {
+            return Err(PngError::BadKeyword);
+        }
+        data.push(0);
+        data.extend(latin1_bytes(text)?);
+        self.insert_before_iend(Chunk::new(ChunkType::TEXT, data));
+        Ok(())
+    }
+
+    /// Inserts the chunk right before the IEND chunk. If there is no IEND
+    /// chunk, the chunk is appended at the end instead and false is
+    /// returned.
+    pub fn insert_before_iend(&mut self, chunk: Chunk) -> bool {
+        match self.chunks.iter().position(|x| *x.chunk_type() == ChunkType::IEND) {
+            Some(idx) => {
+                self.chunks.insert(idx, chunk);
+                true
+            }
+            None => {
+                self.chunks.push(chunk);
+                false
+            }
+        }
+    }
+
+    /// Inserts the chunk where the spec requires it for standard ancillary
+    /// types: color space chunks such as gAMA or sRGB before PLTE, and
+    /// chunks such as bKGD, tRNS or pHYs before the first IDAT. Other
+    /// chunks go before IEND. If none of the chunks to insert before is
+    /// found, the chunk is appended at the end and false is returned.
+    pub fn insert_chunk_sorted(&mut self, chunk: Chunk) -> bool {
+        const BEFORE_PLTE: [&[u8; 4]; 5] = [b"cHRM", b"gAMA", b"iCCP", b"sBIT", b"sRGB"];
+        const BEFORE_IDAT: [&[u8; 4]; 6] = [b"bKGD", b"hIST", b"tRNS", b"pHYs", b"sPLT", b"eXIf"];
+
+        let bytes = chunk.chunk_type().bytes();
+        let anchors: &[ChunkType] = if BEFORE_PLTE.contains(&&bytes) {
+            &[ChunkType::PLTE, ChunkType::IDAT, ChunkType::IEND]
+        } else if BEFORE_IDAT.contains(&&bytes) {
+            &[ChunkType::IDAT, ChunkType::IEND]
+        } else {
+            &[ChunkType::IEND]
+        };
+        let idx = self.chunks.iter().position(|x| anchors.contains(x.chunk_type()));
+        match idx {
+            Some(idx) => {
+                self.chunks.insert(idx, chunk);
+                true
+            }
+            None => {
+                self.chunks.push(chunk);
+                false
+            }
+        }
+    }
+
+    /// Inserts all the chunks, in order, right before the IEND chunk,
+    /// looking IEND up only once. Like `insert_before_iend`, they are
+    /// appended at the end and false is returned if there is no IEND.
+    pub fn append_chunks(&mut self, chunks: Vec<Chunk>) -> bool {
+        match self.chunks.iter().position(|x| *x.chunk_type() == ChunkType::IEND) {
+            Some(idx) => {
+                self.chunks.splice(idx..idx, chunks);
+                true
+            }
+            None => {
+                self.chunks.extend(chunks);
+                false
+            }
+        }
+    }
+
     pub fn remove_chunk(&mut self, chunk_type: &str) -> Result<Chunk, PngError> {
         let chunk_type = ChunkType::from_str(chunk_type).map_err(|e| PngError::Chunk(ChunkError::ChunkType(e)))?;
         let idx = self.chunks.iter().position(|x| *x.chunk_type() == chunk_type).ok_or(PngError::ChunkNotFound)?;
         Ok(self.chunks.remove(idx))        
     }
 
+    /// Like `remove_chunk`, but a missing chunk is not an error, e.g. to
+    /// remove a chunk only if it is there.
+    pub fn remove_chunk_if_present(&mut self, chunk_type: &str) -> Option<Chunk> {
+        self.remove_chunk(chunk_type).ok()
+    }
+
+    /// Replaces the data of the first chunk of the given type, updating its
+    /// length and CRC, and returns the data it held before.
+    pub fn replace_chunk_data_by_type(&mut self, chunk_type: &str, data: Vec<u8>) -> Result<Vec<u8>, PngError> {
+        let chunk_type = ChunkType::from_str(chunk_type).map_err(|e| PngError::Chunk(ChunkError::ChunkType(e)))?;
+        let chunk = self.chunks.iter_mut().find(|x| *x.chunk_type() == chunk_type).ok_or(PngError::ChunkNotFound)?;
+        let old_data = chunk.data().to_vec();
+        chunk.set_data(data);
+        Ok(old_data)
+    }
+
+    /// Keeps only the chunks for which `f` returns true. IHDR, IDAT and
+    /// IEND chunks are always kept, so that the result is still a PNG.
+    pub fn retain_chunks(&mut self, f: impl Fn(&Chunk) -> bool) {
+        self.chunks.retain(|chunk| {
+            let chunk_type = chunk.chunk_type();
+            *chunk_type == ChunkType::IHDR
+                || *chunk_type == ChunkType::IDAT
+                || *chunk_type == ChunkType::IEND
+                || f(chunk)
+        });
+    }
+
     pub fn header(&self) -> &[u8; 8] {
         &Self::STANDARD_HEADER
     }
@@ -62,58 +210,613 @@ impl Png {
         self.chunks.as_ref()
     }
 
+    /// Bytes found after the IEND chunk that do not form chunks, e.g. a
+    /// payload appended to the file. They are written back after the
+    /// last chunk.
+    pub fn trailing_bytes(&self) -> &[u8] {
+        &self.trailing
+    }
+
+    pub fn set_trailing_bytes(&mut self, trailing: Vec<u8>) {
+        self.trailing = trailing;
+    }
+
+    /// Returns the chunks for which `f` returns true, in file order.
+    ///
+    /// ```
+    /// use pngme::chunk::Chunk;
+    /// use pngme::png::Png;
+    ///
+    /// let png = Png::from_chunks(vec![
+    ///     Chunk::try_new("IHDR", vec![0; 13]).unwrap(),
+    ///     Chunk::try_new("tEXt", vec![b'a'; 2048]).unwrap(),
+    ///     Chunk::try_new("ruSt", b"short".to_vec()).unwrap(),
+    /// ]);
+    /// let large_ancillary = png.chunks_matching(|c| !c.chunk_type().is_critical() && c.length() > 1024);
+    /// assert_eq!(large_ancillary.len(), 1);
+    /// assert_eq!(large_ancillary[0].chunk_type().to_string(), "tEXt");
+    /// ```
+    pub fn chunks_matching(&self, f: impl Fn(&Chunk) -> bool) -> Vec<&Chunk> {
+        self.chunks.iter().filter(|chunk| f(chunk)).collect()
+    }
+
+    /// Number of chunks of each type, ordered by chunk type.
+    pub fn chunk_count_by_type(&self) -> BTreeMap<String, usize> {
+        let mut counts = BTreeMap::new();
+        for chunk in &self.chunks {
+            *counts.entry(chunk.chunk_type().to_string()).or_insert(0) += 1;
+        }
+        counts
+    }
+
+    /// Combined data length of every chunk, leaving out the signature and
+    /// the length, type and CRC fields.
+    pub fn total_data_len(&self) -> u64 {
+        self.chunks.iter().map(|chunk| chunk.data().len() as u64).sum()
+    }
+
+    /// Combined data length of the IDAT chunks, i.e. the compressed image
+    /// data.
+    pub fn idat_len(&self) -> u64 {
+        self.chunks
+            .iter()
+            .filter(|chunk| *chunk.chunk_type() == ChunkType::IDAT)
+            .map(|chunk| chunk.data().len() as u64)
+            .sum()
+    }
+
+    /// Removes ancillary chunks that repeat an earlier chunk's type and
+    /// data, keeping either the first or the last one of each group, and
+    /// returns how many chunks were removed. Critical chunks are never
+    /// removed.
+    pub fn deduplicate_chunks(&mut self, keep: DuplicateKeep) -> usize {
+        let num_chunks = self.chunks.len();
+        let mut indices: Vec<usize> = (0..num_chunks).collect();
+        if keep == DuplicateKeep::Last {
+            indices.reverse();
+        }
+
+        let mut seen = HashSet::new();
+        let mut retained = vec![true; num_chunks];
+        for idx in indices {
+            let chunk = &self.chunks[idx];
+            if chunk.chunk_type().is_ancillary() && !seen.insert((chunk.chunk_type().bytes(), chunk.data())) {
+                retained[idx] = false;
+            }
+        }
+
+        let mut retained = retained.into_iter();
+        self.chunks.retain(|_| retained.next().unwrap());
+        num_chunks - self.chunks.len()
+    }
+
+    /// Whether `other` holds the same data, allowing ancillary chunks to
+    /// be in a different order. Critical chunks must match in order, and
+    /// ancillary chunks and trailing bytes must match as a whole.
+    pub fn equivalent_to(&self, other: &Png) -> bool {
+        let is_critical = |chunk: &&Chunk| chunk.chunk_type().is_critical();
+        let is_ancillary = |chunk: &&Chunk| chunk.chunk_type().is_ancillary();
+        if self.trailing != other.trailing
+            || !self.chunks.iter().filter(is_critical).eq(other.chunks.iter().filter(is_critical))
+        {
+            return false;
+        }
+
+        let mut counts: HashMap<&Chunk, isize> = HashMap::new();
+        for chunk in self.chunks.iter().filter(is_ancillary) {
+            *counts.entry(chunk).or_insert(0) += 1;
+        }
+        for chunk in other.chunks.iter().filter(is_ancillary) {
+            *counts.entry(chunk).or_insert(0) -= 1;
+        }
+        counts.values().all(|&count| count == 0)
+    }
+
+    pub fn into_chunks(self) -> Vec<Chunk> {
+        self.chunks
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut Chunk> {
+        self.chunks.iter_mut()
+    }
+
+    /// Returns the first chunk of the given type. When there are several,
+    /// the one nearest the start of the file wins, see `chunk_by_type_last`.
     pub fn chunk_by_type(&self, chunk_type: &str) -> Option<&Chunk> {
         let chunk_type = ChunkType::from_str(chunk_type).ok()?;
         let idx = self.chunks.iter().position(|x| *x.chunk_type() == chunk_type)?;
         Some(&self.chunks[idx]) 
     }
 
+    /// Returns the last chunk of the given type, e.g. the message added by
+    /// the latest encode.
+    pub fn chunk_by_type_last(&self, chunk_type: &str) -> Option<&Chunk> {
+        let chunk_type = ChunkType::from_str(chunk_type).ok()?;
+        self.chunks.iter().rev().find(|x| *x.chunk_type() == chunk_type)
+    }
+
+    /// Splits the chunks after the first chunk of the given type, which
+    /// ends up in the first half. Returns `None` if there is no such chunk.
+    pub fn split_at_type(&self, chunk_type: &str) -> Option<(Vec<&Chunk>, Vec<&Chunk>)> {
+        let chunk_type = ChunkType::from_str(chunk_type).ok()?;
+        let idx = self.chunks.iter().position(|x| *x.chunk_type() == chunk_type)?;
+        let (before, after) = self.chunks.split_at(idx + 1);
+        Some((before.iter().collect(), after.iter().collect()))
+    }
+
+    /// Returns the data of the first chunk of the given type.
+    ///
+    /// ```
+    /// use pngme::chunk::Chunk;
+    /// use pngme::png::Png;
+    ///
+    /// let chunk = Chunk::try_new("ruSt", b"hidden message".to_vec()).unwrap();
+    /// let png = Png::from_chunks(vec![chunk]);
+    /// assert_eq!(png.chunk_data_by_type("ruSt"), Some(&b"hidden message"[..]));
+    /// assert_eq!(png.chunk_data_by_type("TeSt"), None);
+    /// ```
+    pub fn chunk_data_by_type(&self, chunk_type: &str) -> Option<&[u8]> {
+        self.chunk_by_type(chunk_type).map(|c| c.data())
+    }
+
+    /// Returns the first chunk whose stored CRC is `crc`, e.g. to find a
+    /// chunk known from another file.
+    ///
+    /// ```
+    /// use pngme::chunk::Chunk;
+    /// use pngme::png::Png;
+    ///
+    /// let original = Png::from_chunks(vec![Chunk::try_new("ruSt", b"hidden message".to_vec()).unwrap()]);
+    /// let crc = original.chunks()[0].crc();
+    ///
+    /// let copy = Png::from_chunks(vec![
+    ///     Chunk::try_new("teXt", b"something else".to_vec()).unwrap(),
+    ///     Chunk::try_new("ruSt", b"hidden message".to_vec()).unwrap(),
+    /// ]);
+    /// assert_eq!(copy.chunk_by_crc(crc).map(|c| c.data()), Some(&b"hidden message"[..]));
+    /// assert_eq!(copy.chunk_by_crc(crc.wrapping_add(1)), None);
+    /// ```
+    pub fn chunk_by_crc(&self, crc: u32) -> Option<&Chunk> {
+        self.chunks.iter().find(|chunk| chunk.crc() == crc)
+    }
+
+    pub fn index_of_first(&self, chunk_type: &str) -> Option<usize> {
+        let chunk_type = ChunkType::from_str(chunk_type).ok()?;
+        self.chunks.iter().position(|x| *x.chunk_type() == chunk_type)
+    }
+
+    /// Checks the chunk ordering rules of the PNG spec: IHDR first, PLTE
+    /// before the first IDAT, IDAT chunks next to each other and IEND
+    /// last. Only misplaced chunks are reported, not missing ones.
+    pub fn check_order(&self) -> Result<(), PngError> {
+        let positions = |chunk_type: ChunkType| -> Vec<usize> {
+            self.chunks.iter()
+                .enumerate()
+                .filter(|(_, c)| *c.chunk_type() == chunk_type)
+                .map(|(idx, _)| idx)
+                .collect()
+        };
+        if matches!(positions(ChunkType::IHDR).first(), Some(&idx) if idx != 0) {
+            return Err(PngError::IhdrNotFirst);
+        }
+        if matches!(positions(ChunkType::IEND).first(), Some(&idx) if idx != self.chunks.len() - 1) {
+            return Err(PngError::IendNotLast);
+        }
+
+        let idats = positions(ChunkType::IDAT);
+        if let (Some(&first), Some(&last)) = (idats.first(), idats.last()) {
+            if positions(ChunkType::PLTE).iter().any(|&idx| idx > first) {
+                return Err(PngError::PlteAfterIdat);
+            }
+            if last - first + 1 != idats.len() {
+                return Err(PngError::IdatNotContiguous);
+            }
+        }
+        Ok(())
+    }
+
+    pub fn indices_of(&self, chunk_type: &str) -> Vec<usize> {
+        let chunk_type = match ChunkType::from_str(chunk_type) {
+            Ok(chunk_type) => chunk_type,
+            Err(_) => return Vec::new(),
+        };
+        self.chunks.iter()
+        .enumerate()
+        .filter(|(_, x)| *x.chunk_type() == chunk_type)
+        .map(|(idx, _)| idx)
+        .collect()
+    }
+
+    /// Parses a PNG from anything holding its bytes, e.g. a `&[u8]`, a
+    /// `Vec<u8>` or an array.
+    pub fn from_bytes(v: impl AsRef<[u8]>) -> Result<Png, PngError> {
+        Self::try_from(v.as_ref())
+    }
+
+    /// Reads the reader to its end and parses the bytes as a PNG.
+    pub fn from_reader(mut r: impl Read) -> Result<Png, PngError> {
+        let mut v = Vec::new();
+        r.read_to_end(&mut v).map_err(|e| PngError::Io(e.kind()))?;
+        Self::from_bytes(v)
+    }
+
+    /// Parses as many chunks as possible from a damaged or truncated file.
+    /// Returns the chunks that parsed cleanly along with the error that
+    /// stopped parsing, if any.
+    pub fn try_from_partial(v: &[u8]) -> (Png, Option<PngError>) {
+        Self::parse(v, true, false, Self::DEFAULT_MAX_CHUNKS)
+    }
+
+    /// Parses a PNG without verifying chunk CRCs, so that chunks with a
+    /// damaged CRC can still be read. Use `Chunk::is_crc_valid` to find
+    /// out which chunks are affected.
+    pub fn try_from_lenient(v: &[u8]) -> Result<Png, PngError> {
+        match Self::parse(v, false, false, Self::DEFAULT_MAX_CHUNKS) {
+            (png, None) => Ok(png),
+            (_, Some(e)) => Err(e),
+        }
+    }
+
+    /// Once IEND has been read, anything that does not parse as a chunk
+    /// is kept as trailing bytes instead of being an error.
+    fn parse(v: &[u8], check_crc: bool, any_type: bool, max_chunks: usize) -> (Png, Option<PngError>) {
+        let mut png = Self::from_chunks(Vec::new());
+        if v.len() < 8 {
+            return (png, Some(PngError::BadHeader));
+        }
+
+        if v[0..8] != Self::STANDARD_HEADER {
+            let err = match Self::signature_offset(v) {
+                Some(offset) => PngError::BadSignature(offset),
+                None => PngError::BadHeader,
+            };
+            return (png, Some(err));
+        }
+
+        let mut rem = &v[8..];
+        let mut after_iend = false;
+        while !rem.is_empty() {
+            match Self::parse_chunk(rem, check_crc, any_type) {
+                Ok(chunk) => {
+                    if png.chunks.len() == max_chunks {
+                        return (png, Some(PngError::TooManyChunks(max_chunks)));
+                    }
+                    rem = &rem[chunk.total_size()..];
+                    after_iend |= *chunk.chunk_type() == ChunkType::IEND;
+                    png.chunks.push(chunk);
+                }
+                Err(_) if after_iend => {
+                    png.trailing = rem.to_vec();
+                    break;
+                }
+                Err(e) => return (png, Some(e)),
+            }
+        }
+
+        (png, None)
+    }
+
+    /// Whether `v` starts with the PNG signature. Only the first 8 bytes
+    /// are looked at, which makes this a cheap filter before parsing.
+    pub fn is_png_signature(v: &[u8]) -> bool {
+        v.starts_with(&Self::STANDARD_HEADER)
+    }
+
+    /// Position of the PNG signature within the first
+    /// `SIGNATURE_SEARCH_BYTES` bytes of `v`, if there is one.
+    pub fn signature_offset(v: &[u8]) -> Option<usize> {
+        let search = &v[..v.len().min(Self::SIGNATURE_SEARCH_BYTES + Self::STANDARD_HEADER.len())];
+        search.windows(Self::STANDARD_HEADER.len()).position(|w| w == Self::STANDARD_HEADER)
+    }
+
+    /// Parses the chunk at the start of `v`, which may be followed by more
+    /// data.
+    fn parse_chunk(v: &[u8], check_crc: bool, any_type: bool) -> Result<Chunk, PngError> {
+        if v.len() < Chunk::NON_DATA_FIELDS_COMBINED_BYTES {
+            return Err(PngError::BadLen);
+        }
+        let length = u32::from_be_bytes(v[0..4].try_into().unwrap());
+        if length > Chunk::MAX_LENGTH {
+            return Err(PngError::Chunk(ChunkError::LengthTooLarge));
+        }
+        let total_size = length as usize + Chunk::NON_DATA_FIELDS_COMBINED_BYTES;
+        if total_size > v.len() {
+            return Err(PngError::BadLen);
+        }
+        Chunk::parse(&v[..total_size], check_crc, any_type).map_err(PngError::Chunk)
+    }
+
+    /// Serializes the PNG into a single buffer sized up front, without
+    /// building an intermediate buffer per chunk.
     pub fn as_bytes(&self) -> Vec<u8> {
-        let mut res = self.header().to_vec();
+        let mut res = Vec::with_capacity(self.total_size());
+        self.write_to_writer(&mut res).expect("writing to a Vec cannot fail");
+        res
+    }
+
+    /// Size of the serialized PNG, including the signature and any
+    /// trailing bytes.
+    pub fn total_size(&self) -> usize {
+        Self::STANDARD_HEADER.len()
+            + self.chunks.iter().map(|c| c.total_size()).sum::<usize>()
+            + self.trailing.len()
+    }
+
+    /// Writes the serialized PNG chunk by chunk, producing the same bytes
+    /// as `as_bytes` without holding a second copy of the file in memory.
+    pub fn write_to_writer(&self, w: &mut impl Write) -> io::Result<()> {
+        w.write_all(self.header())?;
         for chunk in &self.chunks {
-            res.append(&mut chunk.as_bytes());
+            chunk.write_to(w)?;
         }
-        res
+        w.write_all(&self.trailing)
     }
 }
+fn latin1_bytes(s: &str) -> Result<Vec<u8>, PngError> {
+    s.chars()
+        .map(|c| u8::try_from(c).map_err(|_| PngError::NotLatin1(c)))
+        .collect()
+}
+
 impl TryFrom<&[u8]> for Png {
     type Error = PngError;
     fn try_from(v: &[u8]) -> Result<Self,Self::Error> {
-        if v.len() < 8 {
-            return Err(PngError::BadHeader);
+        match Self::parse(v, true, false, Self::DEFAULT_MAX_CHUNKS) {
+            (png, None) => Ok(png),
+            (_, Some(e)) => Err(e),
         }
+    }
+}
+/// Inserts the chunks before IEND like `Png::append_chunks`, or appends
+/// them if there is no IEND.
+impl Extend<Chunk> for Png {
+    fn extend<I: IntoIterator<Item = Chunk>>(&mut self, iter: I) {
+        self.append_chunks(iter.into_iter().collect());
+    }
+}
+impl AsRef<[Chunk]> for Png {
+    fn as_ref(&self) -> &[Chunk] {
+        self.chunks()
+    }
+}
+impl Display for Png {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        self.formatter().fmt(f)
+    }
+}
 
-        if v[0..8] != Self::STANDARD_HEADER {
-            return Err(PngError::BadHeader);
+/// Configurable textual dump of a `Png`, created by `Png::formatter`.
+///
+/// By default it produces the same output as the `Display` impl of `Png`.
+#[derive(Debug, Clone)]
+pub struct PngFormatter<'a> {
+    png: &'a Png,
+    verbose: bool,
+    limit: Option<usize>,
+    color: bool,
+    check_crc: bool,
+    decimal: bool,
+    explain: bool,
+    index: bool,
+}
+
+impl Png {
+    pub fn formatter(&self) -> PngFormatter<'_> {
+        PngFormatter {
+            png: self,
+            verbose: true,
+            limit: None,
+            color: false,
+            check_crc: false,
+            decimal: false,
+            explain: false,
+            index: false,
         }
+    }
+}
 
-        let mut chunks: Vec<Chunk> = Vec::new();
-        let mut rem = &v[8..];
-        while rem.len() >= 12 {
-            let length = u32::from_be_bytes(rem[0..4].try_into().unwrap()) as usize;
-            if length + Chunk::NON_DATA_FIELDS_COMBINED_BYTES > rem.len() {
-                return Err(PngError::BadLen);
+/// Hex dump of the whole serialized file with every field labelled,
+/// created by `Png::hex_dump`. Each line starts with the file offset and
+/// holds up to 16 bytes of a single field, e.g.
+///
+/// ```text
+/// 00000008  00 00 00 0d                                     ; #1 length: 13
+/// 0000000c  49 48 44 52                                     ; #1 type: IHDR
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct PngHexDump<'a> {
+    png: &'a Png,
+}
+
+impl Png {
+    pub fn hex_dump(&self) -> PngHexDump<'_> {
+        PngHexDump { png: self }
+    }
+}
+
+impl PngHexDump<'_> {
+    const BYTES_PER_LINE: usize = 16;
+
+    /// Writes `bytes` found at `offset`, labelling the first line only.
+    fn fmt_field(f: &mut Formatter<'_>, offset: usize, bytes: &[u8], label: &str) -> std::fmt::Result {
+        for (idx, line) in bytes.chunks(Self::BYTES_PER_LINE).enumerate() {
+            let hex: Vec<String> = line.iter().map(|b| format!("{:02x}", b)).collect();
+            write!(f, "{:08x}  ", offset + idx * Self::BYTES_PER_LINE)?;
+            if idx == 0 {
+                writeln!(f, "{:<width$} ; {}", hex.join(" "), label, width = Self::BYTES_PER_LINE * 3 - 1)?;
+            } else {
+                writeln!(f, "{}", hex.join(" "))?;
             }
-            chunks.push(Chunk::try_from(&rem[..length + Chunk::NON_DATA_FIELDS_COMBINED_BYTES])
-            .map_err(|e| PngError::Chunk(e))?);
-            rem = &rem[length + Chunk::NON_DATA_FIELDS_COMBINED_BYTES..];
         }
+        Ok(())
+    }
+}
 
-        if rem.len() != 0 {
-            return Err(PngError::BadLen);
+impl Display for PngHexDump<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let header = self.png.header();
+        Self::fmt_field(f, 0, header, "signature")?;
+        let mut offset = header.len();
+        for (idx, chunk) in self.png.chunks.iter().enumerate() {
+            let n = idx + 1;
+            Self::fmt_field(f, offset, &chunk.length_bytes(), &format!("#{} length: {}", n, chunk.length()))?;
+            offset += Chunk::LENGTH_FIELD_BYTES;
+            Self::fmt_field(f, offset, &chunk.chunk_type().bytes(), &format!("#{} type: {}", n, chunk.chunk_type()))?;
+            offset += Chunk::CHUNK_TYPE_FIELD_BYTES;
+            Self::fmt_field(f, offset, chunk.data(), &format!("#{} data", n))?;
+            offset += chunk.data().len();
+            Self::fmt_field(f, offset, &chunk.crc_bytes(), &format!("#{} crc", n))?;
+            offset += Chunk::CRC_FIELD_BYTES;
         }
+        Self::fmt_field(f, offset, &self.png.trailing, "trailing data")
+    }
+}
 
-        Ok(Self{
-            chunks,
-        })
+/// Parser with non-default settings, created by `Png::parser`.
+#[derive(Debug, Clone, Copy)]
+pub struct PngParser {
+    check_crc: bool,
+    any_type: bool,
+    max_chunks: usize,
+}
+
+impl Png {
+    pub fn parser() -> PngParser {
+        PngParser {
+            check_crc: true,
+            any_type: false,
+            max_chunks: Self::DEFAULT_MAX_CHUNKS,
+        }
     }
 }
-impl Display for Png {
+
+impl PngParser {
+    /// Whether chunks with a bad CRC are rejected, see `Png::try_from_lenient`.
+    pub fn check_crc(mut self, check_crc: bool) -> Self {
+        self.check_crc = check_crc;
+        self
+    }
+
+    /// Accepts chunk types made of any four bytes instead of only ASCII
+    /// letters, to read files that do not conform to the spec. See
+    /// `ChunkType::from_bytes_unchecked`.
+    pub fn allow_non_ascii_type(mut self, allow: bool) -> Self {
+        self.any_type = allow;
+        self
+    }
+
+    /// Fails with `PngError::TooManyChunks` once the file holds more than
+    /// `max_chunks` chunks.
+    pub fn max_chunks(mut self, max_chunks: usize) -> Self {
+        self.max_chunks = max_chunks;
+        self
+    }
+
+    pub fn parse(&self, v: &[u8]) -> Result<Png, PngError> {
+        match Png::parse(v, self.check_crc, self.any_type, self.max_chunks) {
+            (png, None) => Ok(png),
+            (_, Some(e)) => Err(e),
+        }
+    }
+
+    /// Like `Png::try_from_partial`, keeping the chunks read before the
+    /// first error.
+    pub fn parse_partial(&self, v: &[u8]) -> (Png, Option<PngError>) {
+        Png::parse(v, self.check_crc, self.any_type, self.max_chunks)
+    }
+}
+
+impl<'a> PngFormatter<'a> {
+    /// Whether the chunk data is dumped along with the other fields.
+    pub fn verbose(mut self, verbose: bool) -> Self {
+        self.verbose = verbose;
+        self
+    }
+
+    /// Dumps at most `limit` bytes of data per chunk.
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// Highlights chunk types with ANSI colors, red for critical chunks
+    /// and green for ancillary ones.
+    pub fn color(mut self, color: bool) -> Self {
+        self.color = color;
+        self
+    }
+
+    /// Follows each CRC with `(OK)` or `(BAD)`, telling whether it matches
+    /// the one computed from the chunk type and data.
+    pub fn check_crc(mut self, check_crc: bool) -> Self {
+        self.check_crc = check_crc;
+        self
+    }
+
+    /// Renders CRCs in decimal instead of hex, e.g. to compare them from a
+    /// script rather than in a hex editor.
+    pub fn decimal(mut self, decimal: bool) -> Self {
+        self.decimal = decimal;
+        self
+    }
+
+    /// Follows each chunk type with the properties encoded in it, see
+    /// `ChunkType::describe`.
+    pub fn explain(mut self, explain: bool) -> Self {
+        self.explain = explain;
+        self
+    }
+
+    /// Starts each chunk line with the position of the chunk in `chunks`,
+    /// e.g. `[0]`, padded so that the lines stay aligned.
+    pub fn index(mut self, index: bool) -> Self {
+        self.index = index;
+        self
+    }
+
+    fn fmt_chunk(&self, f: &mut Formatter<'_>, chunk: &Chunk) -> std::fmt::Result {
+        if self.color {
+            let color = if chunk.chunk_type().is_critical() { 31 } else { 32 };
+            write!(f, "Length: {}, Type: \x1b[{}m{}\x1b[0m", chunk.length(), color, chunk.chunk_type())?;
+        } else {
+            write!(f, "Length: {}, Type: {}", chunk.length(), chunk.chunk_type())?;
+        }
+        if self.explain {
+            write!(f, " [{}]", chunk.chunk_type().describe())?;
+        }
+        if self.verbose {
+            let data = chunk.data();
+            match self.limit {
+                Some(limit) if data.len() > limit => {
+                    write!(f, ", Data: {:x?} (+{} bytes)", &data[..limit], data.len() - limit)?
+                }
+                _ => write!(f, ", Data: {:x?}", data)?,
+            }
+        }
+        if self.decimal {
+            write!(f, ", CRC: {}", chunk.crc())?;
+        } else {
+            write!(f, ", CRC: {:x?}", chunk.crc())?;
+        }
+        if self.check_crc {
+            write!(f, " ({})", if chunk.is_crc_valid() { "OK" } else { "BAD" })?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for PngFormatter<'_> {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        let num_chunks = self.chunks.len();
-        write!(f, "HEADER: {:x?}\nCHUNKS: {} chunks in file.\n", self.header(), num_chunks)?;
-        for (idx, chunk) in self.chunks.iter().enumerate() {
-            write!(f, "* CHUNK #[{:03}/{:03}]: {}\n", idx + 1, num_chunks, chunk)?;
+        let num_chunks = self.png.chunks.len();
+        writeln!(f, "HEADER: {:x?}\nCHUNKS: {} chunks in file.", self.png.header(), num_chunks)?;
+        let width = num_chunks.saturating_sub(1).to_string().len();
+        for (idx, chunk) in self.png.chunks.iter().enumerate() {
+            if self.index {
+                write!(f, "[{:>width$}] ", idx, width = width)?;
+            }
+            write!(f, "* CHUNK #[{:03}/{:03}]: ", idx + 1, num_chunks)?;
+            self.fmt_chunk(f, chunk)?;
+            writeln!(f)?;
         }
         Ok(())
     }
@@ -161,6 +864,31 @@ mod tests {
         assert_eq!(png.chunks().len(), 3);
     }
 
+    #[test]
+    fn test_into_chunks() {
+        let png = testing_png();
+        let chunks = png.into_chunks();
+        assert_eq!(chunks, testing_chunks());
+
+        let png = Png::from_chunks(chunks);
+        assert_eq!(png, testing_png());
+    }
+
+    #[test]
+    fn test_iter_mut() {
+        let mut png = testing_png();
+        for chunk in png.iter_mut() {
+            let mut data = chunk.data().to_vec();
+            data.extend_from_slice(b"!");
+            chunk.set_data(data);
+        }
+
+        let png = Png::try_from(png.as_bytes().as_ref()).unwrap();
+        assert_eq!(&png.chunks()[0].data_as_string().unwrap(), "I am the first chunk!");
+        assert_eq!(&png.chunks()[1].data_as_string().unwrap(), "I am another chunk!");
+        assert_eq!(&png.chunks()[2].data_as_string().unwrap(), "I am the last chunk!");
+    }
+
     #[test]
     fn test_valid_from_bytes() {
         let chunk_bytes: Vec<u8> = testing_chunks()
@@ -197,6 +925,30 @@ mod tests {
         assert!(png.is_err());
     }
 
+    #[test]
+    fn test_signature_after_prefix() {
+        let mut bytes = vec![0xef, 0xbb, 0xbf];
+        bytes.extend(testing_png().as_bytes());
+
+        assert_eq!(Png::try_from(bytes.as_ref()), Err(PngError::BadSignature(3)));
+        assert_eq!(
+            PngError::BadSignature(3).to_string(),
+            "Signature found at offset 3; file has a 3-byte prefix"
+        );
+        assert_eq!(Png::try_from(&bytes[3..]), Ok(testing_png()));
+    }
+
+    #[test]
+    fn test_signature_too_far() {
+        let mut bytes = vec![b' '; Png::SIGNATURE_SEARCH_BYTES + 1];
+        bytes.extend(testing_png().as_bytes());
+        assert_eq!(Png::try_from(bytes.as_ref()), Err(PngError::BadHeader));
+
+        let mut bytes = vec![b' '; Png::SIGNATURE_SEARCH_BYTES];
+        bytes.extend(testing_png().as_bytes());
+        assert_eq!(Png::try_from(bytes.as_ref()), Err(PngError::BadSignature(Png::SIGNATURE_SEARCH_BYTES)));
+    }
+
     #[test]
     fn test_invalid_chunk() {
         let mut chunk_bytes: Vec<u8> = testing_chunks()
@@ -220,6 +972,146 @@ mod tests {
     }
 
 
+    #[test]
+    fn test_from_bytes() {
+        let bytes = testing_png().as_bytes();
+        assert_eq!(Png::from_bytes(&bytes[..]), Ok(testing_png()));
+        assert_eq!(Png::from_bytes(&bytes), Ok(testing_png()));
+        assert_eq!(Png::from_bytes(bytes), Ok(testing_png()));
+        assert!(Png::from_bytes(PNG_FILE).is_ok());
+        assert_eq!(Png::from_bytes([0u8; 4]), Err(PngError::BadHeader));
+    }
+
+    #[test]
+    fn test_from_reader() {
+        let bytes = testing_png().as_bytes();
+        assert_eq!(Png::from_reader(&bytes[..]), Ok(testing_png()));
+        assert_eq!(Png::from_reader(io::Cursor::new(bytes)), Ok(testing_png()));
+    }
+
+    #[test]
+    fn test_from_failing_reader() {
+        struct FailingReader;
+        impl Read for FailingReader {
+            fn read(&mut self, _buf: &mut [u8]) -> io::Result<usize> {
+                Err(io::ErrorKind::PermissionDenied.into())
+            }
+        }
+        assert_eq!(Png::from_reader(FailingReader), Err(PngError::Io(io::ErrorKind::PermissionDenied)));
+    }
+
+    #[test]
+    fn test_try_from_partial() {
+        let bytes = testing_png().as_bytes();
+        let (png, err) = Png::try_from_partial(&bytes);
+        assert_eq!(png, testing_png());
+        assert!(err.is_none());
+    }
+
+    #[test]
+    fn test_try_from_partial_truncated() {
+        let bytes = testing_png().as_bytes();
+        // Cut the file off in the middle of the last chunk's data
+        let truncated = &bytes[..bytes.len() - 8];
+
+        assert_eq!(Png::try_from(truncated), Err(PngError::BadLen));
+
+        let (png, err) = Png::try_from_partial(truncated);
+        assert_eq!(png.chunks(), &testing_chunks()[..2]);
+        assert_eq!(err, Some(PngError::BadLen));
+    }
+
+    #[test]
+    fn test_parser_allow_non_ascii_type() {
+        let chunk = Chunk::new(ChunkType::from_bytes_unchecked([0x01, 0x02, 0x03, 0x04]), b"odd".to_vec());
+        let png = Png::from_chunks(vec![chunk]);
+        let bytes = png.as_bytes();
+
+        assert!(matches!(Png::try_from(&bytes[..]), Err(PngError::Chunk(ChunkError::ChunkType(_)))));
+        let parsed = Png::parser().allow_non_ascii_type(true).parse(&bytes).unwrap();
+        assert_eq!(parsed, png);
+        assert!(parsed.to_string().contains("Type: \\x01\\x02\\x03\\x04"));
+    }
+
+    #[test]
+    fn test_parser_max_chunks() {
+        let mut png = Png::from_chunks(Vec::new());
+        for _ in 0..100 {
+            png.append_chunk(chunk_from_strings("tiNy", "").unwrap());
+        }
+        let bytes = png.as_bytes();
+
+        assert_eq!(Png::parser().max_chunks(100).parse(&bytes), Ok(png));
+        assert_eq!(Png::parser().max_chunks(99).parse(&bytes), Err(PngError::TooManyChunks(99)));
+
+        let (partial, err) = Png::parser().max_chunks(10).parse_partial(&bytes);
+        assert_eq!(partial.chunks().len(), 10);
+        assert_eq!(err, Some(PngError::TooManyChunks(10)));
+    }
+
+    #[test]
+    fn test_parser_max_chunks_with_trailing_bytes() {
+        let mut png = testing_png();
+        png.append_chunk(Chunk::new(ChunkType::IEND, Vec::new()));
+        png.set_trailing_bytes(b"This was hidden after IEND".to_vec());
+        let bytes = png.as_bytes();
+
+        let parsed = Png::parser().max_chunks(4).parse(&bytes).unwrap();
+        assert_eq!(parsed.chunks().len(), 4);
+        assert_eq!(parsed.trailing_bytes(), b"This was hidden after IEND");
+        assert_eq!(Png::parser().max_chunks(3).parse(&bytes), Err(PngError::TooManyChunks(3)));
+    }
+
+    #[test]
+    fn test_png_chunk_length_too_large() {
+        let mut bytes = testing_png().as_bytes();
+        let last_chunk = bytes.len() - testing_chunks()[2].total_size();
+        bytes[last_chunk] = 0x80;
+
+        assert_eq!(Png::try_from(bytes.as_ref()), Err(PngError::Chunk(ChunkError::LengthTooLarge)));
+    }
+
+    #[test]
+    fn test_trailing_bytes() {
+        let mut png = testing_png();
+        png.append_chunk(Chunk::new(ChunkType::IEND, Vec::new()));
+        let mut bytes = png.as_bytes();
+        assert_eq!(Png::try_from(bytes.as_ref()).unwrap().trailing_bytes(), b"");
+
+        bytes.extend_from_slice(b"This was hidden after IEND");
+        let png = Png::try_from(bytes.as_ref()).unwrap();
+        assert_eq!(png.chunks().len(), 4);
+        assert_eq!(png.trailing_bytes(), b"This was hidden after IEND");
+        assert_eq!(png.as_bytes(), bytes);
+    }
+
+    #[test]
+    fn test_chunks_after_iend_are_not_trailing() {
+        let mut png = testing_png();
+        png.append_chunk(Chunk::new(ChunkType::IEND, Vec::new()));
+        png.append_chunk(chunk_from_strings("ruSt", "Appended after IEND").unwrap());
+
+        let parsed = Png::try_from(png.as_bytes().as_ref()).unwrap();
+        assert_eq!(parsed.chunks().len(), 5);
+        assert_eq!(parsed.trailing_bytes(), b"");
+    }
+
+    #[test]
+    fn test_try_from_lenient() {
+        let mut bytes = testing_png().as_bytes();
+        // Flip a bit in the CRC of the first chunk
+        let crc_idx = Png::STANDARD_HEADER.len() + testing_chunks()[0].total_size() - 1;
+        bytes[crc_idx] ^= 1;
+
+        assert!(Png::try_from(bytes.as_ref()).is_err());
+
+        let png = Png::try_from_lenient(bytes.as_ref()).unwrap();
+        assert_eq!(png.chunks().len(), 3);
+        assert!(!png.chunks()[0].is_crc_valid());
+        assert!(png.chunks()[1].is_crc_valid());
+        assert_eq!(&png.chunks()[0].data_as_string().unwrap(), "I am the first chunk");
+    }
+
     #[test]
     fn test_list_chunks() {
         let png = testing_png();
@@ -236,6 +1128,29 @@ mod tests {
 
     }
 
+    #[test]
+    fn test_chunk_by_type_last() {
+        let mut png = testing_png();
+        png.append_chunk(chunk_from_strings("miDl", "I am the newer chunk").unwrap());
+
+        assert_eq!(png.chunk_by_type("miDl").unwrap().data(), b"I am another chunk");
+        assert_eq!(png.chunk_by_type_last("miDl").unwrap().data(), b"I am the newer chunk");
+        assert_eq!(png.chunk_by_type_last("FrSt").unwrap().data(), b"I am the first chunk");
+        assert_eq!(png.chunk_by_type_last("TeSt"), None);
+    }
+
+    #[test]
+    fn test_remove_chunk_if_present() {
+        let mut png = testing_png();
+        let chunk = png.remove_chunk_if_present("miDl").unwrap();
+        assert_eq!(chunk.data(), b"I am another chunk");
+        assert_eq!(png.chunks().len(), 2);
+
+        assert_eq!(png.remove_chunk_if_present("miDl"), None);
+        assert_eq!(png.remove_chunk_if_present("TeSt"), None);
+        assert_eq!(png.chunks().len(), 2);
+    }
+
     #[test]
     fn test_append_chunk() {
         let mut png = testing_png();
@@ -245,6 +1160,66 @@ mod tests {
         assert_eq!(&chunk.data_as_string().unwrap(), "Message");
     }
 
+    #[test]
+    fn test_insert_before_iend() {
+        let mut png = testing_png();
+        png.append_chunk(chunk_from_strings("IEND", "").unwrap());
+        assert!(png.insert_before_iend(chunk_from_strings("TeSt", "Message").unwrap()));
+        assert_eq!(png.index_of_first("TeSt"), Some(3));
+        assert_eq!(png.index_of_first("IEND"), Some(4));
+    }
+
+    #[test]
+    fn test_insert_before_iend_without_iend() {
+        let mut png = testing_png();
+        assert!(!png.insert_before_iend(chunk_from_strings("TeSt", "Message").unwrap()));
+        assert_eq!(png.chunks().len(), 4);
+        assert_eq!(png.index_of_first("TeSt"), Some(3));
+    }
+
+    fn chunk_types(png: &Png) -> Vec<String> {
+        png.chunks().iter().map(|c| c.chunk_type().to_string()).collect()
+    }
+
+    #[test]
+    fn test_insert_chunk_sorted() {
+        let mut png = png_from_types(&["IHDR", "PLTE", "IDAT", "IDAT", "IEND"]);
+        assert!(png.insert_chunk_sorted(chunk_from_strings("gAMA", "").unwrap()));
+        assert!(png.insert_chunk_sorted(chunk_from_strings("tRNS", "").unwrap()));
+        assert!(png.insert_chunk_sorted(chunk_from_strings("tEXt", "").unwrap()));
+        assert_eq!(chunk_types(&png), ["IHDR", "gAMA", "PLTE", "tRNS", "IDAT", "IDAT", "tEXt", "IEND"]);
+        assert_eq!(png.check_order(), Ok(()));
+    }
+
+    #[test]
+    fn test_insert_chunk_sorted_without_plte() {
+        let mut png = png_from_types(&["IHDR", "IDAT", "IEND"]);
+        assert!(png.insert_chunk_sorted(chunk_from_strings("gAMA", "").unwrap()));
+        assert_eq!(chunk_types(&png), ["IHDR", "gAMA", "IDAT", "IEND"]);
+
+        let mut png = png_from_types(&["IHDR"]);
+        assert!(!png.insert_chunk_sorted(chunk_from_strings("gAMA", "").unwrap()));
+        assert_eq!(chunk_types(&png), ["IHDR", "gAMA"]);
+    }
+
+    #[test]
+    fn test_append_chunks() {
+        let mut png = testing_png();
+        png.append_chunk(chunk_from_strings("IEND", "").unwrap());
+        let chunks = ["teSa", "teSb", "teSc"].iter().map(|t| chunk_from_strings(t, "").unwrap()).collect();
+        assert!(png.append_chunks(chunks));
+
+        let types: Vec<String> = png.chunks().iter().map(|c| c.chunk_type().to_string()).collect();
+        assert_eq!(types, ["FrSt", "miDl", "LASt", "teSa", "teSb", "teSc", "IEND"]);
+    }
+
+    #[test]
+    fn test_append_chunks_without_iend() {
+        let mut png = testing_png();
+        assert!(!png.append_chunks(vec![chunk_from_strings("TeSt", "").unwrap()]));
+        assert_eq!(png.index_of_first("TeSt"), Some(3));
+    }
+
     #[test]
     fn test_remove_chunk() {
         let mut png = testing_png();
@@ -254,6 +1229,242 @@ mod tests {
         assert!(chunk.is_none());
     }
 
+    #[test]
+    fn test_replace_chunk_data_by_type() {
+        let mut png = testing_png();
+        let old_data = png.replace_chunk_data_by_type("miDl", b"New message".to_vec()).unwrap();
+        assert_eq!(old_data, b"I am another chunk");
+
+        let chunk = png.chunk_by_type("miDl").unwrap();
+        assert_eq!(chunk.data_as_string().unwrap(), "New message");
+        assert_eq!(chunk.length(), 11);
+        assert_eq!(*chunk, chunk_from_strings("miDl", "New message").unwrap());
+        assert!(chunk.is_crc_valid());
+    }
+
+    #[test]
+    fn test_replace_chunk_data_by_missing_type() {
+        let mut png = testing_png();
+        assert_eq!(png.replace_chunk_data_by_type("TeSt", vec![1, 2, 3]), Err(PngError::ChunkNotFound));
+    }
+
+    #[test]
+    fn test_chunk_count_by_type() {
+        let mut png = testing_png();
+        for _ in 0..3 {
+            png.append_chunk(chunk_from_strings("IDAT", "").unwrap());
+        }
+        png.append_chunk(chunk_from_strings("miDl", "I am a duplicate chunk").unwrap());
+
+        let counts = png.chunk_count_by_type();
+        assert_eq!(counts["IDAT"], 3);
+        assert_eq!(counts["miDl"], 2);
+        assert_eq!(counts.keys().collect::<Vec<_>>(), ["FrSt", "IDAT", "LASt", "miDl"]);
+    }
+
+    fn png_from_types(types: &[&str]) -> Png {
+        Png::from_chunks(types.iter().map(|t| chunk_from_strings(t, "").unwrap()).collect())
+    }
+
+    #[test]
+    fn test_split_at_type() {
+        let png = png_from_types(&["IHDR", "gAMA", "IDAT", "IDAT", "tEXt", "IEND"]);
+        let (before, after) = png.split_at_type("IDAT").unwrap();
+        let types = |chunks: Vec<&Chunk>| -> Vec<String> {
+            chunks.iter().map(|c| c.chunk_type().to_string()).collect()
+        };
+        assert_eq!(types(before), ["IHDR", "gAMA", "IDAT"]);
+        assert_eq!(types(after), ["IDAT", "tEXt", "IEND"]);
+
+        let (before, after) = png.split_at_type("IEND").unwrap();
+        assert_eq!(before.len(), 6);
+        assert!(after.is_empty());
+
+        assert!(png.split_at_type("PLTE").is_none());
+        assert!(png.split_at_type("bad!").is_none());
+    }
+
+    #[test]
+    fn test_is_png_signature() {
+        assert!(Png::is_png_signature(&Png::STANDARD_HEADER));
+        assert!(Png::is_png_signature(&PNG_FILE));
+        assert!(!Png::is_png_signature(&Png::STANDARD_HEADER[..7]));
+        assert!(!Png::is_png_signature(b"GIF89a\x01\x00"));
+    }
+
+    #[test]
+    fn test_check_order() {
+        assert_eq!(png_from_types(&["IHDR", "PLTE", "IDAT", "IDAT", "tEXt", "IEND"]).check_order(), Ok(()));
+        assert_eq!(testing_png().check_order(), Ok(()));
+
+        assert_eq!(png_from_types(&["tEXt", "IHDR", "IDAT", "IEND"]).check_order(), Err(PngError::IhdrNotFirst));
+        assert_eq!(png_from_types(&["IHDR", "IDAT", "IEND", "tEXt"]).check_order(), Err(PngError::IendNotLast));
+        assert_eq!(png_from_types(&["IHDR", "IDAT", "PLTE", "IEND"]).check_order(), Err(PngError::PlteAfterIdat));
+        assert_eq!(
+            png_from_types(&["IHDR", "IDAT", "tEXt", "IDAT", "IEND"]).check_order(),
+            Err(PngError::IdatNotContiguous)
+        );
+    }
+
+    #[test]
+    fn test_as_ref_chunks() {
+        fn count_chunks(chunks: impl AsRef<[Chunk]>) -> usize {
+            chunks.as_ref().len()
+        }
+
+        let png = testing_png();
+        assert_eq!(count_chunks(&png), 3);
+        assert_eq!(AsRef::<[Chunk]>::as_ref(&png), png.chunks());
+    }
+
+    fn dedupe_fixture() -> Png {
+        let mut chunks = testing_chunks();
+        chunks.insert(0, chunk_from_strings("tEXt", "Comment").unwrap());
+        chunks.insert(2, chunk_from_strings("tEXt", "Comment").unwrap());
+        chunks.push(chunk_from_strings("tEXt", "Comment").unwrap());
+        Png::from_chunks(chunks)
+    }
+
+    #[test]
+    fn test_deduplicate_chunks_keep_first() {
+        let mut png = dedupe_fixture();
+        assert_eq!(png.deduplicate_chunks(DuplicateKeep::First), 2);
+        let types: Vec<String> = png.chunks().iter().map(|c| c.chunk_type().to_string()).collect();
+        assert_eq!(types, ["tEXt", "FrSt", "miDl", "LASt"]);
+    }
+
+    #[test]
+    fn test_deduplicate_chunks_keep_last() {
+        let mut png = dedupe_fixture();
+        assert_eq!(png.deduplicate_chunks(DuplicateKeep::Last), 2);
+        let types: Vec<String> = png.chunks().iter().map(|c| c.chunk_type().to_string()).collect();
+        assert_eq!(types, ["FrSt", "miDl", "LASt", "tEXt"]);
+    }
+
+    #[test]
+    fn test_deduplicate_chunks_keeps_critical() {
+        let mut png = png_from_types(&["IHDR", "IDAT", "IDAT", "IEND"]);
+        assert_eq!(png.deduplicate_chunks(DuplicateKeep::First), 0);
+        assert_eq!(png.chunks().len(), 4);
+    }
+
+    #[test]
+    fn test_equivalent_to() {
+        let png = png_from_types(&["IHDR", "tEXt", "IDAT", "ruSt", "IEND"]);
+        let reordered = png_from_types(&["IHDR", "ruSt", "IDAT", "IEND", "tEXt"]);
+        assert!(png.equivalent_to(&reordered));
+        assert!(reordered.equivalent_to(&png));
+        assert_ne!(png, reordered);
+
+        let duplicated = png_from_types(&["IHDR", "tEXt", "IDAT", "tEXt", "IEND"]);
+        assert!(!png.equivalent_to(&duplicated));
+        let critical_moved = png_from_types(&["IDAT", "IHDR", "tEXt", "ruSt", "IEND"]);
+        assert!(!png.equivalent_to(&critical_moved));
+
+        let mut with_trailing = png.clone();
+        with_trailing.set_trailing_bytes(b"extra".to_vec());
+        assert!(!png.equivalent_to(&with_trailing));
+    }
+
+    #[test]
+    fn test_extend() {
+        let mut png = crate::testing::minimal_png();
+        png.extend(["tEXt", "ruSt", "zTXt"].iter().map(|t| chunk_from_strings(t, "").unwrap()));
+        assert_eq!(chunk_types(&png), ["IHDR", "IDAT", "tEXt", "ruSt", "zTXt", "IEND"]);
+
+        let mut png = testing_png();
+        png.extend(vec![chunk_from_strings("TeSt", "").unwrap()]);
+        assert_eq!(png.index_of_first("TeSt"), Some(3));
+    }
+
+    #[test]
+    fn test_data_lengths() {
+        let mut png = testing_png();
+        let metadata_len = png.total_data_len();
+        assert_eq!(png.idat_len(), 0);
+
+        png.append_chunk(chunk_from_strings("IDAT", "image").unwrap());
+        png.append_chunk(chunk_from_strings("IDAT", "more image").unwrap());
+        assert_eq!(png.idat_len(), 15);
+        assert_eq!(png.total_data_len(), metadata_len + 15);
+    }
+
+    #[test]
+    fn test_insert_before_type() {
+        let mut png = testing_png();
+        png.append_chunk(chunk_from_strings("miDl", "I am a duplicate chunk").unwrap());
+        png.insert_before_type("miDl", chunk_from_strings("TeSt", "Message").unwrap()).unwrap();
+        assert_eq!(png.index_of_first("TeSt"), Some(1));
+        assert_eq!(png.indices_of("miDl"), vec![2, 4]);
+
+        let result = png.insert_before_type("IDAT", chunk_from_strings("TeSt", "Message").unwrap());
+        assert_eq!(result, Err(PngError::ChunkNotFound));
+        assert_eq!(png.chunks().len(), 5);
+    }
+
+    #[test]
+    fn test_insert_text_chunk() {
+        let mut png = testing_png();
+        png.append_chunk(Chunk::new(ChunkType::IEND, Vec::new()));
+        png.insert_text_chunk("Comment", "Caf\u{e9} au lait").unwrap();
+
+        let chunk = &png.chunks()[3];
+        assert_eq!(chunk.chunk_type().to_string(), "tEXt");
+        let mut parts = chunk.data().splitn(2, |&b| b == 0);
+        assert_eq!(parts.next(), Some(&b"Comment"[..]));
+        assert_eq!(parts.next(), Some(&b"Caf\xe9 au lait"[..]));
+        assert_eq!(png.chunks()[4].chunk_type(), &ChunkType::IEND);
+    }
+
+    #[test]
+    fn test_insert_text_chunk_bad_keyword() {
+        let mut png = testing_png();
+        assert_eq!(png.insert_text_chunk("", "text"), Err(PngError::BadKeyword));
+        assert_eq!(png.insert_text_chunk(&"k".repeat(80), "text"), Err(PngError::BadKeyword));
+        assert_eq!(png.insert_text_chunk("Com\0ment", "text"), Err(PngError::BadKeyword));
+        assert_eq!(png.insert_text_chunk("Comment", "\u{263a}"), Err(PngError::NotLatin1('\u{263a}')));
+        assert!(png.insert_text_chunk(&"k".repeat(79), "text").is_ok());
+    }
+
+    #[test]
+    fn test_index_of_first() {
+        let mut png = testing_png();
+        png.append_chunk(chunk_from_strings("miDl", "I am a duplicate chunk").unwrap());
+        assert_eq!(png.index_of_first("FrSt"), Some(0));
+        assert_eq!(png.index_of_first("miDl"), Some(1));
+        assert_eq!(png.index_of_first("TeSt"), None);
+    }
+
+    #[test]
+    fn test_indices_of() {
+        let mut png = testing_png();
+        png.append_chunk(chunk_from_strings("miDl", "I am a duplicate chunk").unwrap());
+        assert_eq!(png.indices_of("miDl"), vec![1, 3]);
+        assert_eq!(png.indices_of("LASt"), vec![2]);
+        assert!(png.indices_of("TeSt").is_empty());
+        assert!(png.indices_of("Te1t").is_empty());
+    }
+
+    #[test]
+    fn test_retain_chunks() {
+        let mut png = Png::from_chunks(vec![
+            chunk_from_strings("IHDR", "header").unwrap(),
+            chunk_from_strings("PLTE", "palette").unwrap(),
+            chunk_from_strings("teXt", "some text").unwrap(),
+            chunk_from_strings("IDAT", "image data").unwrap(),
+            chunk_from_strings("ruSt", "secret").unwrap(),
+            chunk_from_strings("IEND", "").unwrap(),
+        ]);
+
+        png.retain_chunks(|chunk| chunk.chunk_type().is_ancillary());
+        let types: Vec<String> = png.chunks().iter().map(|c| c.chunk_type().to_string()).collect();
+        assert_eq!(types, vec!["IHDR", "teXt", "IDAT", "ruSt", "IEND"]);
+
+        png.retain_chunks(|_| false);
+        let types: Vec<String> = png.chunks().iter().map(|c| c.chunk_type().to_string()).collect();
+        assert_eq!(types, vec!["IHDR", "IDAT", "IEND"]);
+    }
+
     #[test]
     fn test_png_from_image_file() {
         let png = Png::try_from(&PNG_FILE[..]);
@@ -268,6 +1479,135 @@ mod tests {
         assert_eq!(actual, expected);
     }
 
+    #[test]
+    fn test_as_bytes_matches_chunk_by_chunk() {
+        let mut png = testing_png();
+        png.set_trailing_bytes(b"trailing".to_vec());
+
+        let mut expected = Png::STANDARD_HEADER.to_vec();
+        for chunk in png.chunks() {
+            expected.extend(chunk.length().to_be_bytes());
+            expected.extend(chunk.chunk_type().bytes());
+            expected.extend(chunk.data());
+            expected.extend(chunk.crc().to_be_bytes());
+        }
+        expected.extend(b"trailing");
+
+        let actual = png.as_bytes();
+        assert_eq!(actual, expected);
+        assert_eq!(actual.len(), png.total_size());
+        assert_eq!(actual.capacity(), png.total_size());
+    }
+
+    #[test]
+    fn test_write_to_writer() {
+        let png = Png::try_from(&PNG_FILE[..]).unwrap();
+        let mut bytes = Vec::new();
+        png.write_to_writer(&mut bytes).unwrap();
+        assert_eq!(bytes, png.as_bytes());
+    }
+
+    #[test]
+    fn test_formatter_default() {
+        let png = testing_png();
+        let expected = format!(
+            "HEADER: {:x?}\nCHUNKS: 3 chunks in file.\n* CHUNK #[001/003]: {}\n* CHUNK #[002/003]: {}\n* CHUNK #[003/003]: {}\n",
+            Png::STANDARD_HEADER,
+            png.chunks()[0],
+            png.chunks()[1],
+            png.chunks()[2],
+        );
+        assert_eq!(png.formatter().to_string(), expected);
+        assert_eq!(png.to_string(), expected);
+    }
+
+    #[test]
+    fn test_formatter_options() {
+        let png = Png::from_chunks(vec![chunk_from_strings("TeSt", "Message").unwrap()]);
+        let crc = png.chunks()[0].crc();
+
+        let output = png.formatter().limit(2).to_string();
+        assert!(output.ends_with(&format!("Length: 7, Type: TeSt, Data: [4d, 65] (+5 bytes), CRC: {:x?}\n", crc)));
+
+        let output = png.formatter().verbose(false).to_string();
+        assert!(output.ends_with(&format!("Length: 7, Type: TeSt, CRC: {:x?}\n", crc)));
+
+        let output = png.formatter().color(true).to_string();
+        assert!(output.contains("Type: \x1b[31mTeSt\x1b[0m"));
+    }
+
+    #[test]
+    fn test_formatter_check_crc() {
+        let good = chunk_from_strings("TeSt", "Message").unwrap();
+        let bad = Chunk::from_parts(ChunkType::from_str("TeSt").unwrap(), b"Message".to_vec(), good.crc() + 1);
+        let png = Png::from_chunks(vec![good.clone(), bad]);
+
+        let output = png.formatter().verbose(false).check_crc(true).to_string();
+        assert!(output.contains(&format!("[001/002]: Length: 7, Type: TeSt, CRC: {:x?} (OK)\n", good.crc())));
+        assert!(output.contains(&format!("[002/002]: Length: 7, Type: TeSt, CRC: {:x?} (BAD)\n", good.crc() + 1)));
+    }
+
+    #[test]
+    fn test_hex_dump() {
+        let mut png = Png::from_chunks(vec![
+            chunk_from_strings("ruSt", "This is a secret!").unwrap(),
+            chunk_from_strings("IEND", "").unwrap(),
+        ]);
+        png.set_trailing_bytes(b"xy".to_vec());
+
+        let expected = "\
+00000000  89 50 4e 47 0d 0a 1a 0a                         ; signature
+00000008  00 00 00 11                                     ; #1 length: 17
+0000000c  72 75 53 74                                     ; #1 type: ruSt
+00000010  54 68 69 73 20 69 73 20 61 20 73 65 63 72 65 74 ; #1 data
+00000020  21
+00000021  c8 db 63 00                                     ; #1 crc
+00000025  00 00 00 00                                     ; #2 length: 0
+00000029  49 45 4e 44                                     ; #2 type: IEND
+0000002d  ae 42 60 82                                     ; #2 crc
+00000031  78 79                                           ; trailing data
+";
+        assert_eq!(png.hex_dump().to_string(), expected);
+    }
+
+    #[test]
+    fn test_formatter_explain() {
+        let png = Png::from_chunks(vec![chunk_from_strings("ruSt", "").unwrap()]);
+        let output = png.formatter().verbose(false).explain(true).to_string();
+        assert!(output.contains("Type: ruSt [Ancillary, private, reserved-bit valid, safe to copy], CRC: "));
+    }
+
+    #[test]
+    fn test_formatter_decimal() {
+        let png = Png::from_chunks(vec![Chunk::from_parts(ChunkType::from_str("TeSt").unwrap(), Vec::new(), 0xdeadbeef)]);
+
+        let output = png.formatter().verbose(false).to_string();
+        assert!(output.contains("Type: TeSt, CRC: deadbeef\n"));
+        let output = png.formatter().verbose(false).decimal(true).to_string();
+        assert!(output.contains("Type: TeSt, CRC: 3735928559\n"));
+    }
+
+    #[test]
+    fn test_formatter_index() {
+        let types: Vec<String> = (0..11).map(|i| format!("t{}St", (b'a' + i) as char)).collect();
+        let png = Png::from_chunks(types.iter().map(|t| chunk_from_strings(t, "").unwrap()).collect());
+
+        let output = png.formatter().verbose(false).index(true).to_string();
+        let lines: Vec<&str> = output.lines().skip(2).collect();
+        assert_eq!(lines.len(), png.chunks().len());
+        for (idx, line) in lines.iter().enumerate() {
+            let (prefix, rest) = line.split_once("] * CHUNK").unwrap();
+            assert_eq!(prefix.len(), 3);
+            assert_eq!(prefix[1..].trim().parse::<usize>().unwrap(), idx);
+            assert!(rest.contains(&format!("Type: {}", png.chunks()[idx].chunk_type())));
+        }
+        assert!(lines[0].starts_with("[ 0] * CHUNK #[001/011]: "));
+        assert!(lines[10].starts_with("[10] * CHUNK #[011/011]: "));
+
+        let output = png.formatter().verbose(false).to_string();
+        assert!(output.lines().nth(2).unwrap().starts_with("* CHUNK"));
+    }
+
     #[test]
     fn test_png_trait_impls() {
         let chunk_bytes: Vec<u8> = testing_chunks()
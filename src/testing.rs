@@ -0,0 +1,64 @@
+use crate::chunk::Chunk;
+use crate::chunk_type::ChunkType;
+use crate::png::Png;
+
+/// IHDR data for a 1x1, 8-bit grayscale, non-interlaced image.
+#[rustfmt::skip]
+const IHDR_DATA: [u8; 13] = [
+    0, 0, 0, 1,     // width
+    0, 0, 0, 1,     // height
+    8,              // bit depth
+    0,              // color type
+    0,              // compression method
+    0,              // filter method
+    0,              // interlace method
+];
+
+/// A zlib stream holding a single stored block with the one scanline of
+/// the image: filter type 0 followed by a black pixel.
+#[rustfmt::skip]
+const IDAT_DATA: [u8; 13] = [
+    0x78, 0x01,                 // zlib header
+    0x01, 0x02, 0x00, 0xfd, 0xff, // final stored block of 2 bytes
+    0x00, 0x00,                 // scanline
+    0x00, 0x02, 0x00, 0x01,     // adler32
+];
+
+/// The smallest valid PNG: a single black pixel made of an IHDR, one IDAT
+/// and an IEND chunk.
+pub fn minimal_png() -> Png {
+    Png::from_chunks(vec![
+        Chunk::new(ChunkType::IHDR, IHDR_DATA.to_vec()),
+        Chunk::new(ChunkType::IDAT, IDAT_DATA.to_vec()),
+        Chunk::new(ChunkType::IEND, Vec::new()),
+    ])
+}
+
+/// `minimal_png` serialized, signature included.
+pub fn minimal_png_bytes() -> Vec<u8> {
+    minimal_png().as_bytes()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ihdr::{ColorType, Ihdr};
+
+    #[test]
+    fn test_minimal_png_parses() {
+        let png = Png::try_from(minimal_png_bytes().as_ref()).unwrap();
+        assert_eq!(png, minimal_png());
+        assert!(png.trailing_bytes().is_empty());
+    }
+
+    #[test]
+    fn test_minimal_png_header() {
+        let png = minimal_png();
+        let ihdr = Ihdr::try_from(&png.chunks()[0]).unwrap();
+        assert_eq!((ihdr.width(), ihdr.height()), (1, 1));
+        assert_eq!(ihdr.color_type(), ColorType::Grayscale);
+
+        let chunk_types: Vec<String> = png.chunks().iter().map(|c| c.chunk_type().to_string()).collect();
+        assert_eq!(chunk_types, ["IHDR", "IDAT", "IEND"]);
+    }
+}